@@ -0,0 +1,502 @@
+use crate::auth::backend::MCSKILL_JOIN_URL;
+use crate::auth::yggdrasil::{AuthResponse, MOJANG_AUTH_URL};
+use crate::auth::{AuthBackend, LauncherBackend, MojangBackend, OfflineBackend, SessionCredentials, YggdrasilClient};
+use crate::connection::login::LoginOptions;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// Сколько считать токен живым после выдачи: Yggdrasil не сообщает срок сам
+const TOKEN_LIFETIME: TimeDelta = TimeDelta::hours(24);
+/// Обновлять заранее, чтобы токен не истёк посреди входа
+const REFRESH_MARGIN: TimeDelta = TimeDelta::minutes(5);
+
+/// Где аккаунт авторизуется. Сохраняется в файл вместе с аккаунтом.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackendConfig {
+    Mojang {
+        #[serde(default = "default_mojang_session_url")]
+        session_url: String,
+        #[serde(default = "default_mojang_auth_url")]
+        auth_url: String,
+    },
+    Launcher {
+        #[serde(default = "default_launcher_join_url")]
+        join_url: String,
+        /// Yggdrasil-совместимый адрес лаунчера; без него токен нельзя обновить
+        #[serde(default)]
+        auth_url: Option<String>,
+        #[serde(default = "default_access_token_field")]
+        access_token_field: String,
+        #[serde(default = "default_profile_field")]
+        profile_field: String,
+        #[serde(default = "default_server_id_field")]
+        server_id_field: String,
+    },
+    Offline,
+}
+
+fn default_mojang_session_url() -> String {
+    crate::auth::backend::MOJANG_SESSION_URL.to_string()
+}
+
+fn default_mojang_auth_url() -> String {
+    MOJANG_AUTH_URL.to_string()
+}
+
+fn default_launcher_join_url() -> String {
+    MCSKILL_JOIN_URL.to_string()
+}
+
+fn default_access_token_field() -> String {
+    "accessToken".to_string()
+}
+
+fn default_profile_field() -> String {
+    "selectedProfile".to_string()
+}
+
+fn default_server_id_field() -> String {
+    "serverId".to_string()
+}
+
+impl BackendConfig {
+    pub fn backend(&self) -> Arc<dyn AuthBackend> {
+        match self {
+            BackendConfig::Mojang { session_url, .. } => Arc::new(MojangBackend::new(session_url)),
+            BackendConfig::Launcher {
+                join_url,
+                access_token_field,
+                profile_field,
+                server_id_field,
+                ..
+            } => Arc::new(LauncherBackend::new(join_url).with_fields(
+                access_token_field,
+                profile_field,
+                server_id_field,
+            )),
+            BackendConfig::Offline => Arc::new(OfflineBackend),
+        }
+    }
+
+    /// Клиент для обновления токенов, если бэкенд их поддерживает
+    pub fn yggdrasil(&self) -> Option<YggdrasilClient> {
+        match self {
+            BackendConfig::Mojang { auth_url, .. } => Some(YggdrasilClient::new(auth_url)),
+            BackendConfig::Launcher { auth_url, .. } => auth_url.as_deref().map(YggdrasilClient::new),
+            BackendConfig::Offline => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub username: String,
+    pub backend: BackendConfig,
+    #[serde(default)]
+    pub access_token: Option<String>,
+    #[serde(default)]
+    pub client_token: Option<String>,
+    /// UUID профиля без дефисов
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Account {
+    pub fn new(username: &str, backend: BackendConfig) -> Self {
+        Self {
+            username: username.to_string(),
+            backend,
+            access_token: None,
+            client_token: None,
+            profile_id: None,
+            expires_at: None,
+        }
+    }
+
+    /// Токен есть и до истечения больше `REFRESH_MARGIN`. Срок знаем только у токенов,
+    /// выданных через `sign_in`/обновление; вставленный вручную токен без срока считается годным.
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.access_token.is_some()
+            && self
+                .expires_at
+                .is_none_or(|expires_at| expires_at - REFRESH_MARGIN > now)
+    }
+
+    pub fn credentials(&self) -> SessionCredentials {
+        SessionCredentials {
+            access_token: self.access_token.clone().unwrap_or_default(),
+            selected_profile: self.profile_id.clone().unwrap_or_default(),
+        }
+    }
+
+    fn apply(&mut self, auth: &AuthResponse, now: DateTime<Utc>) {
+        self.access_token = Some(auth.access_token.clone());
+        self.client_token = Some(auth.client_token.clone());
+        if let Some(profile) = &auth.selected_profile {
+            self.profile_id = Some(profile.id.clone());
+            self.username = profile.name.clone();
+        }
+        self.expires_at = Some(now + TOKEN_LIFETIME);
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreFile {
+    accounts: Vec<Account>,
+}
+
+/// Аккаунты с кэшированными токенами в JSON-файле.
+/// Запись атомарная (временный файл + rename), поэтому параллельные боты
+/// видят либо старую, либо новую версию файла, но не обрывок.
+#[derive(Debug)]
+pub struct AccountStore {
+    path: PathBuf,
+    pub accounts: Vec<Account>,
+}
+
+impl AccountStore {
+    /// Загружает хранилище; отсутствующий файл — пустое хранилище
+    pub async fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let accounts = match fs::read(&path).await {
+            Ok(bytes) => {
+                serde_json::from_slice::<StoreFile>(&bytes)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                    .accounts
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, accounts })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, username: &str) -> Option<&Account> {
+        self.accounts.iter().find(|a| a.username == username)
+    }
+
+    /// Добавляет аккаунт или заменяет аккаунт с тем же именем
+    pub fn upsert(&mut self, account: Account) {
+        match self.accounts.iter_mut().find(|a| a.username == account.username) {
+            Some(existing) => *existing = account,
+            None => self.accounts.push(account),
+        }
+    }
+
+    pub fn remove(&mut self, username: &str) -> Option<Account> {
+        let index = self.accounts.iter().position(|a| a.username == username)?;
+        Some(self.accounts.remove(index))
+    }
+
+    pub async fn save(&self) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(&StoreFile {
+            accounts: self.accounts.clone(),
+        })
+        .map_err(io::Error::other)?;
+        write_atomically(&self.path, &json).await
+    }
+
+    /// Вход по паролю через Yggdrasil бэкенда; токены сохраняются в файл
+    pub async fn sign_in(&mut self, username: &str, password: &str, backend: BackendConfig) -> io::Result<&Account> {
+        let client = backend.yggdrasil().ok_or_else(|| no_yggdrasil(username))?;
+        let mut account = self
+            .get(username)
+            .cloned()
+            .unwrap_or_else(|| Account::new(username, backend.clone()));
+        account.backend = backend;
+
+        let auth = client
+            .authenticate(username, password, account.client_token.as_deref())
+            .await?;
+        account.apply(&auth, Utc::now());
+        let name = account.username.clone();
+        self.commit(username, account).await?;
+        Ok(self.get(&name).expect("account was just stored"))
+    }
+
+    /// Готовит `LoginOptions` для аккаунта: при необходимости обновляет токен
+    /// через бэкенд и сохраняет его, затем подставляет учётные данные и бэкенд.
+    pub async fn login_options(&mut self, username: &str) -> io::Result<LoginOptions> {
+        let mut account = self.get(username).cloned().ok_or_else(|| no_account(username))?;
+        if account.backend != BackendConfig::Offline && !account.is_fresh(Utc::now()) {
+            account = self.refresh(username).await?;
+        }
+
+        if account.backend == BackendConfig::Offline {
+            return Ok(LoginOptions::offline(&account.username));
+        }
+        let mut options = LoginOptions::new(&account.username);
+        options.session = account.credentials();
+        options.auth = account.backend.backend();
+        Ok(options)
+    }
+
+    /// Перечитывает файл, чтобы не затереть то, что успели записать другие боты,
+    /// заменяет в нём один аккаунт и сохраняет. Всё это — под блокировкой файла,
+    /// иначе два бота, обновившие токены одновременно, затрут записи друг друга.
+    async fn commit(&mut self, username: &str, account: Account) -> io::Result<()> {
+        let _lock = lock_store(&self.path).await?;
+        let latest = Self::load(&self.path).await?;
+        self.replace(latest, username, account).await
+    }
+
+    /// Обновляет токен под блокировкой файла. Пока мы ждали блокировку, другой бот
+    /// мог уже обновить этот аккаунт и отозвать наш токен — тогда берём его токен из файла.
+    async fn refresh(&mut self, username: &str) -> io::Result<Account> {
+        let _lock = lock_store(&self.path).await?;
+        let latest = Self::load(&self.path).await?;
+        let mut account = latest
+            .get(username)
+            .or_else(|| self.get(username))
+            .cloned()
+            .ok_or_else(|| no_account(username))?;
+
+        let now = Utc::now();
+        if account.is_fresh(now) {
+            self.accounts = latest.accounts;
+            return Ok(account);
+        }
+        let client = account.backend.yggdrasil().ok_or_else(|| no_yggdrasil(username))?;
+        let (Some(access_token), Some(client_token)) = (&account.access_token, &account.client_token) else {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("Account {} has no cached token, sign in first", username),
+            ));
+        };
+        let auth = client.refresh(access_token, client_token).await?;
+        account.apply(&auth, now);
+        self.replace(latest, username, account.clone()).await?;
+        Ok(account)
+    }
+
+    /// Заменяет аккаунт в свежепрочитанном `latest` и сохраняет. Вызывается под блокировкой.
+    async fn replace(&mut self, mut latest: Self, username: &str, account: Account) -> io::Result<()> {
+        latest.remove(username);
+        latest.upsert(account);
+        latest.save().await?;
+        self.accounts = latest.accounts;
+        Ok(())
+    }
+}
+
+fn no_account(username: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("No account named {}", username))
+}
+
+fn no_yggdrasil(username: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Auth backend of {} cannot issue tokens", username),
+    )
+}
+
+/// Эксклюзивная блокировка рядом лежащего `<файл>.lock`, в том числе между процессами.
+/// Снимается, когда возвращённый файл закрывается.
+async fn lock_store(path: &Path) -> io::Result<std::fs::File> {
+    let mut lock_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Account store path has no file name"))?
+        .to_os_string();
+    lock_name.push(".lock");
+    let lock_path = path.with_file_name(lock_name);
+
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        file.lock()?;
+        Ok(file)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+async fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Account store path has no file name"))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(format!(".{}.tmp", uuid::Uuid::new_v4().simple()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let write = async {
+        let mut file = fs::File::create(&tmp_path).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        drop(file);
+        fs::rename(&tmp_path, path).await
+    };
+    let result = write.await;
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path).await;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::http;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn temp_store_path() -> PathBuf {
+        std::env::temp_dir().join(format!("accounts-{}.json", uuid::Uuid::new_v4().simple()))
+    }
+
+    #[tokio::test]
+    async fn save_and_load_round_trip() {
+        let path = temp_store_path();
+        let mut store = AccountStore::load(&path).await.unwrap();
+        assert!(store.accounts.is_empty());
+
+        store.upsert(Account::new("offline_bot", BackendConfig::Offline));
+        store.upsert(Account::new(
+            "mcskill_bot",
+            serde_json::from_value(json!({ "type": "launcher" })).unwrap(),
+        ));
+        store.save().await.unwrap();
+
+        let loaded = AccountStore::load(&path).await.unwrap();
+        assert_eq!(loaded.accounts, store.accounts);
+        let dir_entries: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_name().to_string_lossy().starts_with(&*path.file_name().unwrap().to_string_lossy()))
+            .collect();
+        assert_eq!(dir_entries.len(), 1, "temporary file left behind");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn commit_waits_for_store_lock() {
+        let path = temp_store_path();
+        let mut first = AccountStore::load(&path).await.unwrap();
+        let mut second = AccountStore::load(&path).await.unwrap();
+
+        let lock = lock_store(&path).await.unwrap();
+        let pending = tokio::spawn(async move {
+            second.commit("b", Account::new("b", BackendConfig::Offline)).await.unwrap();
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!pending.is_finished(), "commit ignored the lock");
+        drop(lock);
+        pending.await.unwrap();
+
+        first.commit("a", Account::new("a", BackendConfig::Offline)).await.unwrap();
+        let names: Vec<_> = first.accounts.iter().map(|a| a.username.as_str()).collect();
+        assert_eq!(names, ["b", "a"]);
+        std::fs::remove_file(&path).unwrap();
+        let _ = std::fs::remove_file(path.with_extension("json.lock"));
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_refreshed_before_login() {
+        let auth_url = http::serve_local(|request| {
+            let body: Value = serde_json::from_str(&request.body).unwrap();
+            if request.path == "/refresh" && body["accessToken"] == "old" {
+                let profile = json!({ "id": "00112233445566778899aabbccddeeff", "name": "bot" });
+                (200, json!({ "accessToken": "new", "clientToken": "client", "selectedProfile": profile }).to_string())
+            } else {
+                (403, json!({ "error": "ForbiddenOperationException" }).to_string())
+            }
+        })
+        .await;
+
+        let path = temp_store_path();
+        let mut store = AccountStore::load(&path).await.unwrap();
+        let mut account = Account::new(
+            "bot",
+            BackendConfig::Mojang {
+                session_url: default_mojang_session_url(),
+                auth_url,
+            },
+        );
+        account.access_token = Some("old".to_string());
+        account.client_token = Some("client".to_string());
+        account.expires_at = Some(Utc::now() - TimeDelta::hours(1));
+        store.upsert(account);
+        store.save().await.unwrap();
+
+        let options = store.login_options("bot").await.unwrap();
+        assert_eq!(options.session.access_token, "new");
+        assert_eq!(options.session.selected_profile, "00112233445566778899aabbccddeeff");
+
+        let saved = AccountStore::load(&path).await.unwrap();
+        let saved = saved.get("bot").unwrap();
+        assert_eq!(saved.access_token.as_deref(), Some("new"));
+        assert!(saved.is_fresh(Utc::now()));
+
+        // Свежий токен используется из кэша, без запроса к серверу
+        assert_eq!(store.login_options("bot").await.unwrap().session.access_token, "new");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pasted_token_without_expiry_is_used() {
+        let path = temp_store_path();
+        let mut store = AccountStore::load(&path).await.unwrap();
+        let mut account = Account::new("bot", serde_json::from_value(json!({ "type": "launcher" })).unwrap());
+        account.access_token = Some("pasted".to_string());
+        account.profile_id = Some("profile".to_string());
+        store.upsert(account);
+
+        // Без auth_url обновить токен нечем, но он и не нужен
+        let options = store.login_options("bot").await.unwrap();
+        assert_eq!(options.session.access_token, "pasted");
+        assert_eq!(options.session.selected_profile, "profile");
+    }
+
+    #[tokio::test]
+    async fn concurrent_bots_refresh_once() {
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let counter = refreshes.clone();
+        let auth_url = http::serve_local(move |request| {
+            let body: Value = serde_json::from_str(&request.body).unwrap();
+            // Обновление отзывает старый токен: второй запрос с ним получит 403
+            let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+            if request.path == "/refresh" && body["accessToken"] == "old" && first {
+                (200, json!({ "accessToken": "new", "clientToken": "client" }).to_string())
+            } else {
+                (403, json!({ "error": "ForbiddenOperationException" }).to_string())
+            }
+        })
+        .await;
+
+        let path = temp_store_path();
+        let mut account = Account::new(
+            "bot",
+            BackendConfig::Mojang {
+                session_url: default_mojang_session_url(),
+                auth_url,
+            },
+        );
+        account.access_token = Some("old".to_string());
+        account.client_token = Some("client".to_string());
+        account.expires_at = Some(Utc::now() - TimeDelta::hours(1));
+        let mut first = AccountStore::load(&path).await.unwrap();
+        first.upsert(account);
+        first.save().await.unwrap();
+        let mut second = AccountStore::load(&path).await.unwrap();
+
+        let (a, b) = tokio::join!(first.login_options("bot"), second.login_options("bot"));
+        assert_eq!(a.unwrap().session.access_token, "new");
+        assert_eq!(b.unwrap().session.access_token, "new");
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+        std::fs::remove_file(&path).unwrap();
+        let _ = std::fs::remove_file(path.with_extension("json.lock"));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::auth::SessionCredentials;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{Map, Value};
+use std::fmt::Debug;
+use std::io;
+
+/// join-эндпоинт лаунчера mcskill
+pub const MCSKILL_JOIN_URL: &str = "https://launcher.mcskill.net/joinserver1710.php";
+/// Базовый адрес сессионного сервера Mojang
+pub const MOJANG_SESSION_URL: &str = "https://sessionserver.mojang.com";
+
+/// Куда и как сообщать о входе на сервер в online-режиме.
+/// Вызывается во время шифрования, до отправки `EncryptionResponse`.
+#[async_trait]
+pub trait AuthBackend: Debug + Send + Sync {
+    async fn join(&self, credentials: &SessionCredentials, server_hash: &str) -> io::Result<()>;
+}
+
+/// Сессионный сервер в стиле Mojang: `POST {base}/session/minecraft/join`
+#[derive(Debug, Clone)]
+pub struct MojangBackend {
+    pub base_url: String,
+}
+
+impl MojangBackend {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl Default for MojangBackend {
+    fn default() -> Self {
+        Self::new(MOJANG_SESSION_URL)
+    }
+}
+
+#[async_trait]
+impl AuthBackend for MojangBackend {
+    async fn join(&self, credentials: &SessionCredentials, server_hash: &str) -> io::Result<()> {
+        let body = serde_json::json!({
+            "accessToken": credentials.access_token,
+            "selectedProfile": credentials.selected_profile,
+            "serverId": server_hash
+        });
+        post_join(&format!("{}/session/minecraft/join", self.base_url), &body).await
+    }
+}
+
+/// join-эндпоинт стороннего лаунчера: свой адрес и свои имена полей в JSON-теле
+#[derive(Debug, Clone)]
+pub struct LauncherBackend {
+    pub url: String,
+    pub access_token_field: String,
+    pub profile_field: String,
+    pub server_id_field: String,
+    /// Постоянные поля, которые лаунчер ждёт в теле помимо учётных данных
+    pub extra_fields: Map<String, Value>,
+}
+
+impl LauncherBackend {
+    /// Эндпоинт с теми же именами полей, что у Mojang
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            access_token_field: "accessToken".to_string(),
+            profile_field: "selectedProfile".to_string(),
+            server_id_field: "serverId".to_string(),
+            extra_fields: Map::new(),
+        }
+    }
+
+    pub fn mcskill() -> Self {
+        Self::new(MCSKILL_JOIN_URL)
+    }
+
+    pub fn with_fields(mut self, access_token: &str, profile: &str, server_id: &str) -> Self {
+        self.access_token_field = access_token.to_string();
+        self.profile_field = profile.to_string();
+        self.server_id_field = server_id.to_string();
+        self
+    }
+
+    pub fn with_extra(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.extra_fields.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+#[async_trait]
+impl AuthBackend for LauncherBackend {
+    async fn join(&self, credentials: &SessionCredentials, server_hash: &str) -> io::Result<()> {
+        let mut body = self.extra_fields.clone();
+        body.insert(self.access_token_field.clone(), credentials.access_token.clone().into());
+        body.insert(self.profile_field.clone(), credentials.selected_profile.clone().into());
+        body.insert(self.server_id_field.clone(), server_hash.into());
+        post_join(&self.url, &Value::Object(body)).await
+    }
+}
+
+/// Offline-режим: сессионного сервера нет, join ничего не делает
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OfflineBackend;
+
+#[async_trait]
+impl AuthBackend for OfflineBackend {
+    async fn join(&self, _credentials: &SessionCredentials, _server_hash: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn post_join(url: &str, body: &Value) -> io::Result<()> {
+    let resp = Client::new()
+        .post(url)
+        .json(body)
+        .send()
+        .await
+        .map_err(io::Error::other)?;
+
+    let status = resp.status();
+    let text = resp.text().await.map_err(io::Error::other)?;
+
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Session join failed: status={} body={}", status, text),
+        ))
+    }
+}
@@ -0,0 +1,132 @@
+//! Минимальный HTTP/1.1-сервер для локальных заглушек: один запрос на соединение,
+//! ответ — JSON. Ни keep-alive, ни chunked-тел не поддерживает.
+
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+pub(crate) struct Request {
+    pub method: String,
+    /// Путь вместе со строкой запроса
+    pub path: String,
+    pub body: String,
+}
+
+impl Request {
+    /// Путь без строки запроса
+    pub fn route(&self) -> &str {
+        self.path.split_once('?').map_or(self.path.as_str(), |(route, _)| route)
+    }
+
+    /// Параметр строки запроса, раскодированный из `application/x-www-form-urlencoded`
+    pub fn query(&self, key: &str) -> Option<String> {
+        let (_, query) = self.path.split_once('?')?;
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| url_decode(k) == key)
+            .map(|(_, v)| url_decode(v))
+    }
+}
+
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    out.push(byte);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Обслуживает `listener` в фоновой задаче. `responder` получает каждый запрос
+/// и возвращает код ответа и тело.
+pub(crate) fn serve<F>(listener: TcpListener, responder: F) -> JoinHandle<()>
+where
+    F: Fn(Request) -> (u16, String) + Send + Sync + 'static,
+{
+    let responder = Arc::new(responder);
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let responder = responder.clone();
+            tokio::spawn(async move {
+                let _ = handle(socket, &*responder).await;
+            });
+        }
+    })
+}
+
+/// Для тестов: сервер на свободном локальном порту, возвращает `http://127.0.0.1:port`
+#[cfg(test)]
+pub(crate) async fn serve_local<F>(responder: F) -> String
+where
+    F: Fn(Request) -> (u16, String) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    serve(listener, responder);
+    base_url
+}
+
+async fn handle<F>(mut socket: TcpStream, responder: &F) -> std::io::Result<()>
+where
+    F: Fn(Request) -> (u16, String),
+{
+    let mut request = Vec::new();
+    let mut chunk = [0u8; 1024];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&request[..header_end]).into_owned();
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let length: usize = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    while request.len() < header_end + length {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&chunk[..n]);
+    }
+
+    let body = String::from_utf8_lossy(&request[header_end..]).into_owned();
+    let (status, response) = responder(Request { method, path, body });
+    let head = format!(
+        "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        response.len()
+    );
+    socket.write_all(head.as_bytes()).await?;
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
@@ -1,8 +1,24 @@
-use std::error::Error;
-use reqwest::Client;
+use md5::Md5;
 use sha1::{Sha1, Digest};
+use uuid::Uuid;
 
-const MC_URL: &'static str = "https://launcher.mcskill.net/joinserver1710.php";
+pub mod accounts;
+pub mod backend;
+pub mod yggdrasil;
+pub(crate) mod http;
+pub mod session_server;
+
+pub use accounts::{Account, AccountStore, BackendConfig};
+pub use backend::{AuthBackend, LauncherBackend, MojangBackend, OfflineBackend};
+pub use session_server::SessionServer;
+pub use yggdrasil::YggdrasilClient;
+
+/// Учётные данные для join на сессионном сервере во время шифрования.
+#[derive(Debug, Clone, Default)]
+pub struct SessionCredentials {
+    pub access_token: String,
+    pub selected_profile: String,
+}
 
 fn digest_to_mc_hex(digest: [u8; 20]) -> String {
     let negative = (digest[0] & 0x80) != 0;
@@ -34,46 +50,36 @@ fn digest_to_mc_hex(digest: [u8; 20]) -> String {
     }
 }
 
-pub async fn join_auth_server(
-    server_id: &str,
-    shared_secret: &[u8],
-    public_key: &[u8],
-    access_token: &str,
-    selected_profile: &str,
-) -> Result<(), Box<dyn Error>> {
+/// Хэш сервера для join/hasJoined: SHA-1 от server_id, общего секрета и ключа сервера
+/// в знаковом шестнадцатеричном виде, как `BigInteger.toString(16)` в Java
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
     let mut hasher = Sha1::new();
     hasher.update(server_id.as_bytes());
     hasher.update(shared_secret);
     hasher.update(public_key);
-    let digest = hasher.finalize(); 
-
-    let server_hash_hex = digest_to_mc_hex(digest.into());
-
-    println!("Server Hash hex: {}", server_hash_hex);
-    let body = serde_json::json!({
-        "accessToken": access_token,
-        "selectedProfile": selected_profile,
-        "serverId": server_hash_hex
-    });
+    digest_to_mc_hex(hasher.finalize().into())
+}
 
-    let client = Client::new();
-    let resp = client
-        .post(MC_URL.to_string())
-        .json(&body)
-        .send()
-        .await?;
+/// UUID, который offline-сервер выдаст игроку: `UUID.nameUUIDFromBytes("OfflinePlayer:" + name)`
+pub fn offline_uuid(username: &str) -> Uuid {
+    let digest = Md5::digest(format!("OfflinePlayer:{}", username).as_bytes());
+    uuid::Builder::from_md5_bytes(digest.into()).into_uuid()
+}
 
-    let status = resp.status();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let text = resp.text().await?;
+    #[test]
+    fn offline_uuid_matches_java() {
+        assert_eq!(offline_uuid("Notch").to_string(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+        assert_eq!(offline_uuid("Notch").get_version_num(), 3);
+    }
 
-    if status == reqwest::StatusCode::OK {
-        println!("{}", text);
-        Ok(())
-    } else {
-        Err(format!(
-            "joinAuthServer failed: status={} body={}",
-            status, text
-        ))?
+    #[test]
+    fn server_hash_matches_java_biginteger() {
+        assert_eq!(server_hash("Notch", &[], &[]), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+        assert_eq!(server_hash("jeb_", &[], &[]), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(server_hash("simon", &[], &[]), "88e16a1019277b15d58faf0541e11910eb756f6");
     }
-}
\ No newline at end of file
+}
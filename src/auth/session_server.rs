@@ -0,0 +1,204 @@
+use crate::auth::http::{self, Request};
+use crate::auth::server_hash;
+use crate::auth::yggdrasil::Profile;
+use crate::auth::MojangBackend;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+const JOIN_ROUTE: &str = "/session/minecraft/join";
+const HAS_JOINED_ROUTE: &str = "/session/minecraft/hasJoined";
+
+#[derive(Debug, Default)]
+struct SessionState {
+    /// access token -> профиль, которому он выдан
+    profiles: HashMap<String, Profile>,
+    /// хэш сервера -> профиль, сделавший join
+    joins: HashMap<String, Profile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JoinRequest {
+    access_token: String,
+    selected_profile: String,
+    server_id: String,
+}
+
+/// Локальная замена сессионного сервера Mojang для тестов и mock-серверов.
+/// Принимает `POST /session/minecraft/join` от ботов и отвечает на
+/// `GET /session/minecraft/hasJoined?username=&serverId=` так же, как настоящий:
+/// 200 с профилем или 204, если такого join не было.
+/// Останавливается при удалении.
+#[derive(Debug)]
+pub struct SessionServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<SessionState>>,
+    task: JoinHandle<()>,
+}
+
+impl SessionServer {
+    /// Запускает сервер на `addr`, например `"127.0.0.1:0"` для свободного порта
+    pub async fn bind(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(SessionState::default()));
+        let task = http::serve(listener, {
+            let state = state.clone();
+            move |request| respond(&state, request)
+        });
+        Ok(Self { addr, state, task })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Бэкенд, через который бот делает join именно на этот сервер
+    pub fn backend(&self) -> MojangBackend {
+        MojangBackend::new(&self.base_url())
+    }
+
+    /// Выдаёт `access_token` профилю. join с незарегистрированным токеном отклоняется.
+    pub fn register(&self, access_token: &str, profile: Profile) {
+        self.lock().profiles.insert(access_token.to_string(), profile);
+    }
+
+    /// То же, что `hasJoined`, без HTTP
+    pub fn has_joined(&self, username: &str, server_hash: &str) -> Option<Profile> {
+        self.lock()
+            .joins
+            .get(server_hash)
+            .filter(|profile| profile.name == username)
+            .cloned()
+    }
+
+    /// Проверка со стороны игрового сервера: считает хэш из того, что он отправил
+    /// в `EncryptionRequest`, и расшифрованного общего секрета
+    pub fn verify(&self, username: &str, server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> Option<Profile> {
+        self.has_joined(username, &server_hash(server_id, shared_secret, public_key))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for SessionServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn respond(state: &Mutex<SessionState>, request: Request) -> (u16, String) {
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+    match (request.method.as_str(), request.route()) {
+        ("POST", JOIN_ROUTE) => {
+            let Ok(join) = serde_json::from_str::<JoinRequest>(&request.body) else {
+                return (400, error("IllegalArgumentException", "Malformed join request"));
+            };
+            match state.profiles.get(&join.access_token) {
+                Some(profile) if profile.id == join.selected_profile => {
+                    let profile = profile.clone();
+                    state.joins.insert(join.server_id, profile);
+                    (204, String::new())
+                }
+                _ => (403, error("ForbiddenOperationException", "Invalid token.")),
+            }
+        }
+        ("GET", HAS_JOINED_ROUTE) => {
+            let (Some(username), Some(server_id)) = (request.query("username"), request.query("serverId")) else {
+                return (400, error("IllegalArgumentException", "username and serverId are required"));
+            };
+            match state.joins.get(&server_id).filter(|p| p.name == username) {
+                Some(profile) => (
+                    200,
+                    json!({ "id": profile.id, "name": profile.name, "properties": [] }).to_string(),
+                ),
+                None => (204, String::new()),
+            }
+        }
+        _ => (404, error("Not Found", "The server has not found anything matching the request URI")),
+    }
+}
+
+fn error(error: &str, message: &str) -> String {
+    json!({ "error": error, "errorMessage": message }).to_string()
+}
+
+/// Запрос `hasJoined` к сессионному серверу по адресу `session_url`, как делает игровой сервер.
+/// `None` — бот не делал join с этим хэшем.
+pub async fn has_joined(session_url: &str, username: &str, server_hash: &str) -> io::Result<Option<Profile>> {
+    let resp = Client::new()
+        .get(format!("{}{}", session_url.trim_end_matches('/'), HAS_JOINED_ROUTE))
+        .query(&[("username", username), ("serverId", server_hash)])
+        .send()
+        .await
+        .map_err(io::Error::other)?;
+
+    match resp.status() {
+        StatusCode::OK => {
+            let body: Value = resp.json().await.map_err(io::Error::other)?;
+            serde_json::from_value(body)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        StatusCode::NO_CONTENT => Ok(None),
+        status => Err(io::Error::other(format!("hasJoined failed: status={}", status))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthBackend, SessionCredentials};
+
+    fn bot() -> Profile {
+        Profile {
+            id: "0123456789abcdef0123456789abcdef".to_string(),
+            name: "bot".to_string(),
+        }
+    }
+
+    fn credentials(access_token: &str) -> SessionCredentials {
+        SessionCredentials {
+            access_token: access_token.to_string(),
+            selected_profile: bot().id,
+        }
+    }
+
+    #[tokio::test]
+    async fn join_then_has_joined() {
+        let server = SessionServer::bind("127.0.0.1:0").await.unwrap();
+        server.register("token", bot());
+
+        // Отрицательный хэш проверяет и знак, и URL-кодирование
+        let hash = "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1";
+        server.backend().join(&credentials("token"), hash).await.unwrap();
+
+        assert_eq!(has_joined(&server.base_url(), "bot", hash).await.unwrap(), Some(bot()));
+        assert_eq!(has_joined(&server.base_url(), "other", hash).await.unwrap(), None);
+        assert_eq!(has_joined(&server.base_url(), "bot", "1").await.unwrap(), None);
+        assert_eq!(server.has_joined("bot", hash), Some(bot()));
+    }
+
+    #[tokio::test]
+    async fn join_with_unknown_token_is_rejected() {
+        let server = SessionServer::bind("127.0.0.1:0").await.unwrap();
+        server.register("token", bot());
+
+        let err = server.backend().join(&credentials("stolen"), "abc").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(server.has_joined("bot", "abc"), None);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::auth::SessionCredentials;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io;
+
+/// Базовый адрес сервера авторизации Mojang
+pub const MOJANG_AUTH_URL: &str = "https://authserver.mojang.com";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    /// UUID профиля без дефисов — именно его ждёт join как `selectedProfile`
+    pub id: String,
+    pub name: String,
+}
+
+/// Ответ `authenticate` и `refresh`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthResponse {
+    pub access_token: String,
+    pub client_token: String,
+    #[serde(default)]
+    pub available_profiles: Vec<Profile>,
+    pub selected_profile: Option<Profile>,
+}
+
+impl AuthResponse {
+    /// Учётные данные для join; без выбранного профиля `selected_profile` пустой
+    pub fn credentials(&self) -> SessionCredentials {
+        SessionCredentials {
+            access_token: self.access_token.clone(),
+            selected_profile: self
+                .selected_profile
+                .as_ref()
+                .map(|p| p.id.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_message: String,
+}
+
+/// Клиент Yggdrasil: `authenticate`, `refresh`, `validate`, `invalidate`, `signout`.
+/// Лаунчеры вроде mcskill отдают совместимые эндпоинты по своему адресу.
+#[derive(Debug, Clone)]
+pub struct YggdrasilClient {
+    base_url: String,
+    client: Client,
+}
+
+impl YggdrasilClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Вход по логину и паролю. Без `client_token` сервер выдаст новый.
+    pub async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        client_token: Option<&str>,
+    ) -> io::Result<AuthResponse> {
+        let mut body = json!({
+            "agent": { "name": "Minecraft", "version": 1 },
+            "username": username,
+            "password": password,
+            "requestUser": false
+        });
+        if let Some(token) = client_token {
+            body["clientToken"] = token.into();
+        }
+        let text = self.post("authenticate", &body).await?;
+        parse_auth_response(&text)
+    }
+
+    /// Новый access token взамен старого; старый после этого недействителен
+    pub async fn refresh(&self, access_token: &str, client_token: &str) -> io::Result<AuthResponse> {
+        let body = json!({
+            "accessToken": access_token,
+            "clientToken": client_token
+        });
+        let text = self.post("refresh", &body).await?;
+        parse_auth_response(&text)
+    }
+
+    /// `true` — токен можно использовать для join, `false` — его нужно обновить
+    pub async fn validate(&self, access_token: &str, client_token: Option<&str>) -> io::Result<bool> {
+        let mut body = json!({ "accessToken": access_token });
+        if let Some(token) = client_token {
+            body["clientToken"] = token.into();
+        }
+        match self.post("validate", &body).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn invalidate(&self, access_token: &str, client_token: &str) -> io::Result<()> {
+        let body = json!({
+            "accessToken": access_token,
+            "clientToken": client_token
+        });
+        self.post("invalidate", &body).await.map(|_| ())
+    }
+
+    /// Отзывает все токены аккаунта
+    pub async fn signout(&self, username: &str, password: &str) -> io::Result<()> {
+        let body = json!({
+            "username": username,
+            "password": password
+        });
+        self.post("signout", &body).await.map(|_| ())
+    }
+
+    /// POST на `{base_url}/{endpoint}`. Ответ с ошибкой превращается в `io::Error`:
+    /// 401/403 — `PermissionDenied`, остальное — `Other`.
+    async fn post(&self, endpoint: &str, body: &Value) -> io::Result<String> {
+        let resp = self
+            .client
+            .post(format!("{}/{}", self.base_url, endpoint))
+            .json(body)
+            .send()
+            .await
+            .map_err(io::Error::other)?;
+
+        let status = resp.status();
+        let text = resp.text().await.map_err(io::Error::other)?;
+        if status.is_success() {
+            return Ok(text);
+        }
+
+        let message = match serde_json::from_str::<ErrorResponse>(&text) {
+            Ok(error) => format!("{}: {}", error.error, error.error_message),
+            Err(_) => format!("status={} body={}", status, text),
+        };
+        let kind = match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::Other,
+        };
+        Err(io::Error::new(kind, format!("Yggdrasil {} failed: {}", endpoint, message)))
+    }
+}
+
+impl Default for YggdrasilClient {
+    fn default() -> Self {
+        Self::new(MOJANG_AUTH_URL)
+    }
+}
+
+fn parse_auth_response(text: &str) -> io::Result<AuthResponse> {
+    serde_json::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::http::{self, Request};
+
+    const ACCESS: &str = "access-1";
+    const CLIENT: &str = "client-1";
+    const PROFILE_ID: &str = "0123456789abcdef0123456789abcdef";
+
+    /// Ведёт себя как authserver: один аккаунт `bot`/`secret`, один действующий токен
+    fn authserver(request: Request) -> (u16, String) {
+        let body: Value = serde_json::from_str(&request.body).unwrap_or_default();
+        let forbidden = || {
+            (
+                403,
+                json!({ "error": "ForbiddenOperationException", "errorMessage": "Invalid credentials." })
+                    .to_string(),
+            )
+        };
+        let session = |access: &str| {
+            json!({
+                "accessToken": access,
+                "clientToken": CLIENT,
+                "availableProfiles": [{ "id": PROFILE_ID, "name": "bot" }],
+                "selectedProfile": { "id": PROFILE_ID, "name": "bot" }
+            })
+            .to_string()
+        };
+        let credentials_ok = body["username"] == "bot" && body["password"] == "secret";
+
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/authenticate") if credentials_ok && body["agent"]["name"] == "Minecraft" => {
+                (200, session(ACCESS))
+            }
+            ("POST", "/refresh") if body["accessToken"] == ACCESS && body["clientToken"] == CLIENT => {
+                (200, session("access-2"))
+            }
+            ("POST", "/validate") if body["accessToken"] == ACCESS => (204, String::new()),
+            ("POST", "/invalidate") if body["accessToken"] == ACCESS => (204, String::new()),
+            ("POST", "/signout") if credentials_ok => (204, String::new()),
+            ("POST", _) => forbidden(),
+            _ => (404, String::new()),
+        }
+    }
+
+    async fn client() -> YggdrasilClient {
+        YggdrasilClient::new(&http::serve_local(authserver).await)
+    }
+
+    #[tokio::test]
+    async fn authenticate_returns_session() {
+        let client = client().await;
+        let auth = client.authenticate("bot", "secret", Some(CLIENT)).await.unwrap();
+        assert_eq!(auth.access_token, ACCESS);
+        assert_eq!(auth.client_token, CLIENT);
+        assert_eq!(auth.available_profiles.len(), 1);
+
+        let credentials = auth.credentials();
+        assert_eq!(credentials.access_token, ACCESS);
+        assert_eq!(credentials.selected_profile, PROFILE_ID);
+    }
+
+    #[tokio::test]
+    async fn authenticate_reports_error_message() {
+        let err = client().await.authenticate("bot", "wrong", None).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("Invalid credentials."));
+    }
+
+    #[tokio::test]
+    async fn refresh_issues_new_token() {
+        let client = client().await;
+        assert_eq!(client.refresh(ACCESS, CLIENT).await.unwrap().access_token, "access-2");
+        assert!(client.refresh("stale", CLIENT).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn validate_distinguishes_stale_tokens() {
+        let client = client().await;
+        assert!(client.validate(ACCESS, Some(CLIENT)).await.unwrap());
+        assert!(!client.validate("stale", None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn invalidate_and_signout() {
+        let client = client().await;
+        client.invalidate(ACCESS, CLIENT).await.unwrap();
+        client.signout("bot", "secret").await.unwrap();
+        assert!(client.signout("bot", "wrong").await.is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::connection::connection_handle::ConnectionHandle;
+use async_trait::async_trait;
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+use tokio::io;
+
+/// Служебные каналы: списки каналов через `\0`, которые сторона готова принимать
+pub const REGISTER_CHANNEL: &str = "REGISTER";
+pub const UNREGISTER_CHANNEL: &str = "UNREGISTER";
+
+/// Обработчик сообщений одного канала
+#[async_trait]
+pub trait ChannelHandler: Send {
+    async fn handle(&mut self, conn: &ConnectionHandle, data: &[u8]) -> io::Result<()>;
+}
+
+#[async_trait]
+impl<F> ChannelHandler for F
+where
+    F: FnMut(&ConnectionHandle, &[u8]) -> io::Result<()> + Send,
+{
+    async fn handle(&mut self, conn: &ConnectionHandle, data: &[u8]) -> io::Result<()> {
+        self(conn, data)
+    }
+}
+
+/// Сообщение канала плагина с собственным форматом
+#[async_trait]
+pub trait PluginMessage: Sized + Send {
+    async fn decode(data: &[u8]) -> io::Result<Self>;
+    async fn encode(&self) -> io::Result<Vec<u8>>;
+}
+
+/// Разбирает данные в `M` и отдаёт в замыкание, см. [`ChannelRouter::on`]
+pub struct Typed<M, F> {
+    f: F,
+    _message: PhantomData<fn() -> M>,
+}
+
+#[async_trait]
+impl<M, F> ChannelHandler for Typed<M, F>
+where
+    M: PluginMessage + 'static,
+    F: FnMut(&ConnectionHandle, M) -> io::Result<()> + Send,
+{
+    async fn handle(&mut self, conn: &ConnectionHandle, data: &[u8]) -> io::Result<()> {
+        let message = M::decode(data).await?;
+        (self.f)(conn, message)
+    }
+}
+
+/// Каналы плагинов: наши обработчики и то, что зарегистрировал сервер.
+/// Служебные каналы (`REGISTER`, `FML|HS`) разбирает ядро соединения, сюда
+/// попадает всё остальное.
+#[derive(Default)]
+pub struct ChannelRouter {
+    handlers: HashMap<String, Box<dyn ChannelHandler>>,
+    /// Каналы, которые сервер объявил через `REGISTER`
+    server_channels: BTreeSet<String>,
+}
+
+impl ChannelRouter {
+    /// Каналы, для которых есть обработчик, — их клиент объявляет серверу
+    pub fn channels(&self) -> BTreeSet<String> {
+        self.handlers.keys().cloned().collect()
+    }
+
+    pub fn server_channels(&self) -> &BTreeSet<String> {
+        &self.server_channels
+    }
+
+    pub fn server_has(&self, channel: &str) -> bool {
+        self.server_channels.contains(channel)
+    }
+
+    /// Возвращает `true`, если канал новый и его нужно объявить серверу
+    pub fn insert<H>(&mut self, channel: &str, handler: H) -> bool
+    where
+        H: ChannelHandler + 'static,
+    {
+        self.handlers
+            .insert(channel.to_string(), Box::new(handler))
+            .is_none()
+    }
+
+    /// Как [`ChannelRouter::insert`], но данные сначала разбираются в `M`
+    pub fn on<M, F>(&mut self, channel: &str, f: F) -> bool
+    where
+        M: PluginMessage + 'static,
+        F: FnMut(&ConnectionHandle, M) -> io::Result<()> + Send + 'static,
+    {
+        self.insert(
+            channel,
+            Typed {
+                f,
+                _message: PhantomData,
+            },
+        )
+    }
+
+    pub fn remove(&mut self, channel: &str) -> bool {
+        self.handlers.remove(channel).is_some()
+    }
+
+    pub(crate) fn server_registered(&mut self, data: &[u8]) {
+        self.server_channels.extend(split_channels(data));
+    }
+
+    pub(crate) fn server_unregistered(&mut self, data: &[u8]) {
+        for channel in split_channels(data) {
+            self.server_channels.remove(&channel);
+        }
+    }
+
+    /// Сообщение без обработчика молча пропускается
+    pub(crate) async fn dispatch(&mut self, conn: &ConnectionHandle, channel: &str, data: &[u8]) -> io::Result<()> {
+        match self.handlers.get_mut(channel) {
+            Some(handler) => handler.handle(conn, data).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Debug for ChannelRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChannelRouter")
+            .field("channels", &self.channels())
+            .field("server_channels", &self.server_channels)
+            .finish()
+    }
+}
+
+/// Данные `REGISTER`/`UNREGISTER`: имена каналов через `\0`
+pub fn join_channels<'a>(channels: impl IntoIterator<Item = &'a str>) -> Vec<u8> {
+    channels.into_iter().collect::<Vec<_>>().join("\0").into_bytes()
+}
+
+pub fn split_channels(data: &[u8]) -> Vec<String> {
+    data.split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_lists_round_trip() {
+        let data = join_channels(["WECUI", "BungeeCord"]);
+        assert_eq!(data, b"WECUI\0BungeeCord");
+        assert_eq!(split_channels(&data), ["WECUI", "BungeeCord"]);
+        // Некоторые серверы завершают список нулём
+        assert_eq!(split_channels(b"A\0B\0"), ["A", "B"]);
+    }
+
+    #[test]
+    fn tracks_server_channels() {
+        let mut router = ChannelRouter::default();
+        router.server_registered(b"WECUI\0BungeeCord");
+        router.server_unregistered(b"WECUI");
+        assert!(router.server_has("BungeeCord"));
+        assert!(!router.server_has("WECUI"));
+    }
+}
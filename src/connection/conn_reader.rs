@@ -1,57 +1,53 @@
-use tokio::io::{self, AsyncRead, AsyncWrite, BufReader, ReadBuf};
-use tokio::net::TcpStream;
+use tokio::io::{self, AsyncRead, BufReader, ReadBuf};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use crate::protocol::crypto::EncryptedStream; 
+use crate::protocol::crypto::EncryptedStream;
 
-pub enum ConnReader {
-    Plain(BufReader<TcpStream>),
-    Encrypted(BufReader<EncryptedStream>),
-}
+/// Читающая половина любого транспорта: половина TCP-сокета, прокси, `duplex`
+pub type ReadHalf = Box<dyn AsyncRead + Unpin + Send>;
 
-impl AsyncRead for ConnReader {
-    fn poll_read(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &mut ReadBuf<'_>,
-    ) -> Poll<io::Result<()>> {
-        match &mut *self {
-            ConnReader::Plain(r) => Pin::new(r).poll_read(cx, buf),
-            ConnReader::Encrypted(r) => Pin::new(r).poll_read(cx, buf),
-        }
-    }
+/// Читающая половина соединения. Живёт в цикле чтения `Connection::run`.
+#[allow(clippy::large_enum_variant)]
+pub enum ConnReader {
+    Plain(BufReader<ReadHalf>),
+    Encrypted(BufReader<EncryptedStream<ReadHalf>>),
 }
 
-impl AsyncWrite for ConnReader {
-    fn poll_write(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        data: &[u8],
-    ) -> Poll<io::Result<usize>> {
-        match &mut *self {
-            ConnReader::Plain(r) => Pin::new(r.get_mut()).poll_write(cx, data),
-            ConnReader::Encrypted(r) => Pin::new(r.get_mut()).poll_write(cx, data),
-        }
+impl ConnReader {
+    pub fn new(half: ReadHalf) -> Self {
+        ConnReader::Plain(BufReader::new(half))
     }
 
-    fn poll_flush(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<io::Result<()>> {
-        match &mut *self {
-            ConnReader::Plain(r) => Pin::new(r.get_mut()).poll_flush(cx),
-            ConnReader::Encrypted(r) => Pin::new(r.get_mut()).poll_flush(cx),
+    /// Переключает половину на расшифровку.
+    /// Всё, что уже лежит в буфере, пришло открытым текстом, поэтому непустой буфер — ошибка.
+    pub fn into_encrypted(self, key: &[u8]) -> io::Result<Self> {
+        match self {
+            ConnReader::Plain(plain_buf) => {
+                if !plain_buf.buffer().is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unencrypted data buffered while enabling encryption",
+                    ));
+                }
+                let half = plain_buf.into_inner();
+                let encrypted = EncryptedStream::new(half, key)?;
+                Ok(ConnReader::Encrypted(BufReader::new(encrypted)))
+            }
+            ConnReader::Encrypted(_) => Err(io::Error::other("Already encrypted")),
         }
     }
+}
 
-    fn poll_shutdown(
+impl AsyncRead for ConnReader {
+    fn poll_read(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
         match &mut *self {
-            ConnReader::Plain(r) => Pin::new(r.get_mut()).poll_shutdown(cx),
-            ConnReader::Encrypted(r) => Pin::new(r.get_mut()).poll_shutdown(cx),
+            ConnReader::Plain(r) => Pin::new(r).poll_read(cx, buf),
+            ConnReader::Encrypted(r) => Pin::new(r).poll_read(cx, buf),
         }
     }
 }
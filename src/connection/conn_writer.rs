@@ -0,0 +1,120 @@
+use tokio::io::{self, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{broadcast, mpsc};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::connection::events::{ConnectionEvent, EventKind};
+use crate::protocol::crypto::EncryptedStream;
+
+/// Команды для задачи-писателя. Порядок в очереди — порядок на проводе.
+#[derive(Debug)]
+pub enum Outbound {
+    /// Уже сериализованный пакет вместе с префиксом длины
+    Packet(Vec<u8>),
+    /// Всё, что поставлено в очередь после этой команды, уходит зашифрованным
+    EnableEncryption([u8; 16]),
+}
+
+/// Пишущая половина любого транспорта
+pub type WriteHalf = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Пишущая половина соединения. Ей владеет только задача из [`spawn_writer`].
+#[allow(clippy::large_enum_variant)]
+pub enum ConnWriter {
+    Plain(WriteHalf),
+    Encrypted(EncryptedStream<WriteHalf>),
+}
+
+impl ConnWriter {
+    pub fn new(half: WriteHalf) -> Self {
+        ConnWriter::Plain(half)
+    }
+
+    pub fn into_encrypted(self, key: &[u8]) -> io::Result<Self> {
+        match self {
+            ConnWriter::Plain(half) => Ok(ConnWriter::Encrypted(EncryptedStream::new(half, key)?)),
+            ConnWriter::Encrypted(_) => Err(io::Error::other("Already encrypted")),
+        }
+    }
+}
+
+/// Запускает задачу, которая разбирает исходящую очередь и пишет в сокет.
+/// Задача завершается, когда закрыты все отправители (все `ConnectionHandle` дропнуты).
+/// Ошибка записи публикуется в `events` как `EventKind::Error`: после неё все отправки
+/// падают, и это единственный способ узнать причину.
+pub fn spawn_writer(
+    writer: ConnWriter,
+    outbound: mpsc::Receiver<Outbound>,
+    events: broadcast::Sender<ConnectionEvent>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = write_loop(writer, outbound).await {
+            let _ = events.send(ConnectionEvent::now(EventKind::from(&e)));
+        }
+    })
+}
+
+async fn write_loop(mut writer: ConnWriter, mut outbound: mpsc::Receiver<Outbound>) -> io::Result<()> {
+    while let Some(cmd) = outbound.recv().await {
+        match cmd {
+            Outbound::Packet(bytes) => {
+                writer.write_all(&bytes).await?;
+                writer.flush().await?;
+            }
+            Outbound::EnableEncryption(key) => writer = writer.into_encrypted(&key)?,
+        }
+    }
+    writer.shutdown().await
+}
+
+impl AsyncWrite for ConnWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut *self {
+            ConnWriter::Plain(w) => Pin::new(w).poll_write(cx, data),
+            ConnWriter::Encrypted(w) => Pin::new(w).poll_write(cx, data),
+        }
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut *self {
+            ConnWriter::Plain(w) => Pin::new(w).poll_flush(cx),
+            ConnWriter::Encrypted(w) => Pin::new(w).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut *self {
+            ConnWriter::Plain(w) => Pin::new(w).poll_shutdown(cx),
+            ConnWriter::Encrypted(w) => Pin::new(w).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn write_errors_are_published() {
+        let (client, server) = io::duplex(64);
+        drop(server);
+        let (outbound_tx, outbound_rx) = mpsc::channel(1);
+        let (events_tx, mut events) = broadcast::channel(1);
+        let writer = spawn_writer(ConnWriter::new(Box::new(client)), outbound_rx, events_tx);
+
+        outbound_tx.send(Outbound::Packet(vec![1, 0x00])).await.unwrap();
+        writer.await.unwrap();
+        let event = events.recv().await.unwrap();
+        assert!(matches!(event.kind, EventKind::Error { kind: io::ErrorKind::BrokenPipe, .. }));
+    }
+}
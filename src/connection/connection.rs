@@ -1,103 +1,389 @@
-use crate::connection::conn_reader::ConnReader;
+use crate::connection::channels::{ChannelHandler, ChannelRouter, PluginMessage};
+use crate::connection::conn_reader::{ConnReader, ReadHalf};
+use crate::connection::conn_writer::{spawn_writer, ConnWriter, WriteHalf};
+use crate::connection::connection_handle::ConnectionHandle;
 use crate::connection::connection_state::ConnectionState;
-use crate::protocol::packets::decoder::read_server_packet_by_state;
+use crate::connection::events::{ConnectionEvent, EventKind};
+use crate::connection::handshake::split_address;
+use crate::auth::{AuthBackend, LauncherBackend, SessionCredentials};
+use crate::protocol::packets::decoder::{decode_server_packet, take_frame};
+use crate::protocol::channels::minecraft::DEFAULT_BRAND;
+use crate::protocol::chat::{self, Language};
+use crate::protocol::fields::{VarInt, VarString};
+use crate::protocol::fml::{FmlHandshake, IdRegistry, MultipartAssembler};
 use crate::protocol::packets::server::*;
-use crate::protocol::packets::AsyncPacket;
-use tokio::io;
-use tokio::io::BufReader;
+use crate::world::{EntityTracker, World};
+use crate::protocol::packets::{AsyncPacket, AsyncPacketExt, ClientStatus, TabComplete};
+use std::time::Duration;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite};
 use tokio::net::TcpStream;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
 
-#[macro_export]
-macro_rules! process_packet {
-    ($reader:expr, $state:expr, $handler:expr, $packet_enum:ident) => {{
-        let boxed_packet = read_server_packet_by_state($reader, $state).await?;
-        if let Some(packet) = $packet_enum::try_from(boxed_packet) {
-            packet.handle_by($handler).await;
-        } else {
-            // Unknown, give up
-        }
-    }};
-}
+/// Сколько исходящих пакетов может ждать записи, прежде чем `send_packet` начнёт ждать
+const OUTBOUND_QUEUE_SIZE: usize = 256;
+/// `ClientStatus.action_id`, на который сервер отвечает пакетом `Statistics`
+const CLIENT_STATUS_REQUEST_STATS: i32 = 1;
 
 pub struct Connection {
-    pub state: ConnectionState,
     pub entity_id: Option<i32>,
 
-    /// Может быть `Some(ConnReader::Plain(...))` или `Some(ConnReader::Encrypted(...))`.
+    /// Читающая половина. Может быть `Some(ConnReader::Plain(...))` или `Some(ConnReader::Encrypted(...))`.
     /// Если `None`, значит мы «вынули» поток или соединение разорвано.
     reader: Option<ConnReader>,
+    /// Прочитанные, но ещё не разобранные байты. Благодаря ему чтение можно
+    /// прервать таймаутом, не потеряв половину пакета.
+    inbound: Vec<u8>,
+    /// Пишущая половина живёт в отдельной задаче, сюда ведёт только очередь.
+    handle: ConnectionHandle,
+    /// Адрес, переданный в `connect`, и адрес, к которому реально подключились
+    addr: String,
+    peer: String,
+    /// С чем ходить на сессионный сервер, если сервер попросит шифрование
+    pub(crate) session: SessionCredentials,
+    pub(crate) auth: Arc<dyn AuthBackend>,
+    /// Рукопожатие Forge, которое ведёт ядро соединения по сообщениям `FML|HS`
+    pub(crate) fml: FmlHandshake,
+    /// Обработчики каналов плагинов и каналы, объявленные сервером
+    pub(crate) channels: ChannelRouter,
+    /// `MC|Brand`, который уходит после `JoinGame`. `None` — не отправлять.
+    pub(crate) brand: Option<String>,
+    /// Недособранное сообщение `FML|MP`
+    pub(crate) multipart: MultipartAssembler,
+    /// `Connected` ещё не опубликован: подписаться можно только после `connect`,
+    /// поэтому событие уходит, когда впервые запускается чтение
+    announce_pending: bool,
 }
 
 impl Connection {
-    /// Подключается к указанному адресу, оборачивает в BufReader (Plain)
+    /// Подключается к указанному адресу, делит сокет на половины и запускает задачу-писателя
     pub async fn connect(addr: &str) -> io::Result<Self> {
         let tcp = TcpStream::connect(addr).await?;
-        let reader = ConnReader::Plain(BufReader::new(tcp));
-        Ok(Self {
-            state: ConnectionState::Handshaking,
+        let peer = tcp.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| addr.to_string());
+        let (read_half, write_half) = tcp.into_split();
+        Ok(Self::from_halves(Box::new(read_half), Box::new(write_half), addr, peer))
+    }
+
+    /// Соединение поверх уже установленного транспорта: прокси, TLS-туннеля,
+    /// `tokio::io::duplex` в тестах. `addr` — адрес сервера для `Handshake`.
+    pub fn from_stream<S>(stream: S, addr: &str) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (read_half, write_half) = io::split(stream);
+        Self::from_halves(Box::new(read_half), Box::new(write_half), addr, addr.to_string())
+    }
+
+    fn from_halves(read_half: ReadHalf, write_half: WriteHalf, addr: &str, peer: String) -> Self {
+        let (outbound_tx, outbound_rx) = mpsc::channel(OUTBOUND_QUEUE_SIZE);
+        let handle = ConnectionHandle::new(outbound_tx);
+        spawn_writer(ConnWriter::new(write_half), outbound_rx, handle.event_sender());
+
+        Self {
             entity_id: None,
-            reader: Some(reader),
-        })
+            reader: Some(ConnReader::new(read_half)),
+            inbound: Vec::new(),
+            handle,
+            addr: addr.to_string(),
+            peer,
+            session: SessionCredentials::default(),
+            auth: Arc::new(LauncherBackend::mcskill()),
+            fml: FmlHandshake::default(),
+            channels: ChannelRouter::default(),
+            brand: Some(DEFAULT_BRAND.to_string()),
+            multipart: MultipartAssembler::default(),
+            announce_pending: true,
+        }
+    }
+
+    /// Клонируемая ручка для отправки пакетов из других задач
+    pub fn handle(&self) -> ConnectionHandle {
+        self.handle.clone()
+    }
+
+    /// Подписка на события соединения: пакеты, вход, шифрование, отключение, ошибки
+    pub fn subscribe(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.handle.subscribe()
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.handle.state()
+    }
+
+    /// Таблица id блоков и предметов из `ModIdData`, см. [`IdRegistry`]
+    pub fn registry(&self) -> Arc<IdRegistry> {
+        self.handle().registry()
+    }
+
+    /// Мир, собранный из пакетов чанков, см. [`ConnectionHandle::world`]
+    pub fn world(&self) -> std::sync::MutexGuard<'_, World> {
+        self.handle.world()
+    }
+
+    /// Сущности вокруг бота, см. [`ConnectionHandle::entities`]
+    pub fn entities(&self) -> std::sync::MutexGuard<'_, EntityTracker> {
+        self.handle.entities()
+    }
+
+    /// Состояние рукопожатия FML и всё, что сервер прислал в нём
+    pub fn fml(&self) -> &FmlHandshake {
+        &self.fml
+    }
+
+    /// Например, чтобы поменять список модов до входа
+    pub fn fml_mut(&mut self) -> &mut FmlHandshake {
+        &mut self.fml
+    }
+
+    pub fn channels(&self) -> &ChannelRouter {
+        &self.channels
+    }
+
+    /// Вешает обработчик на канал плагина. Новый канал объявляется серверу через `REGISTER`:
+    /// сразу, если вход уже выполнен, иначе — после `LoginSuccess`.
+    pub async fn register_channel<H>(&mut self, channel: &str, handler: H) -> io::Result<()>
+    where
+        H: ChannelHandler + 'static,
+    {
+        if self.channels.insert(channel, handler) && self.state() == ConnectionState::Play {
+            self.handle.register_channels([channel]).await?;
+        }
+        Ok(())
+    }
+
+    /// Как [`Connection::register_channel`], но данные сначала разбираются в `M`
+    pub async fn on_channel<M, F>(&mut self, channel: &str, f: F) -> io::Result<()>
+    where
+        M: PluginMessage + 'static,
+        F: FnMut(&ConnectionHandle, M) -> io::Result<()> + Send + 'static,
+    {
+        if self.channels.on(channel, f) && self.state() == ConnectionState::Play {
+            self.handle.register_channels([channel]).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn unregister_channel(&mut self, channel: &str) -> io::Result<()> {
+        if self.channels.remove(channel) && self.state() == ConnectionState::Play {
+            self.handle.unregister_channels([channel]).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn send_plugin_message(&self, channel: &str, data: &[u8]) -> io::Result<()> {
+        self.handle.send_plugin_message(channel, data).await
+    }
+
+    pub fn set_state(&self, state: ConnectionState) {
+        self.handle.set_state(state)
+    }
+
+    /// Основной цикл чтения входящих пакетов.
+    /// Каждый пакет сначала обрабатывает ядро соединения (keepalive, шифрование, смена
+    /// состояния), затем он уходит в `handler`. Цикл завершается после пакета отключения.
+    /// Состояние берётся после прихода пакета, поэтому его можно менять из других задач.
+    pub async fn run<H>(&mut self, handler: &mut H) -> io::Result<()>
+    where
+        H: ServerPacketHandler,
+    {
+        self.announce();
+        let result = self.run_loop(handler).await;
+        if let Err(e) = &result {
+            self.handle.publish(EventKind::from(e));
+            self.handle.publish(EventKind::Disconnected { reason: None });
+        }
+        result
     }
 
-    /// Основной цикл чтения входящих пакетов
-    pub async fn run(&mut self) -> io::Result<()> {
+    async fn run_loop<H>(&mut self, handler: &mut H) -> io::Result<()>
+    where
+        H: ServerPacketHandler,
+    {
         loop {
-            let r = match self.reader.as_mut() {
-                Some(r) => r,
-                None => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "No connection reader available",
-                    ));
-                }
+            let (_, Some(packet)) = self.read_packet().await? else {
+                continue;
             };
 
-            process_packet!(&mut *r, self.state, self, ServerPacket);
+            let disconnect_reason = disconnect_reason(&packet);
+            packet.handle_by(handler).await;
+            if let Some(reason) = disconnect_reason {
+                self.handle.publish(EventKind::Disconnected { reason: Some(reason) });
+                return Ok(());
+            }
         }
     }
 
-    pub async fn send_packet<P>(&mut self, packet: &P)
+    /// Ждёт следующий пакет типа `T`, читая соединение самостоятельно (без обработчика).
+    /// Для сценариев и тестов, где `run` не запущен; если он запущен в другой задаче,
+    /// используйте [`ConnectionHandle::wait_for`].
+    pub async fn wait_for<T>(&mut self, timeout: Duration) -> io::Result<T>
     where
-        P: AsyncPacket,
+        T: Clone + 'static,
     {
-        let r = match self.reader.as_mut() {
-            Some(r) => r,
-            None => {
-                eprintln!("No connection reader available, can't send packet");
-                return;
+        self.wait_for_matching(|_: &T| true, timeout).await
+    }
+
+    /// Как [`Connection::wait_for`], но пропускает пакеты, не прошедшие `predicate`.
+    /// Пропущенные пакеты проходят через ядро соединения и публикуются подписчикам.
+    /// Отключение сервером во время ожидания — ошибка `ConnectionAborted`.
+    pub async fn wait_for_matching<T, F>(&mut self, mut predicate: F, timeout: Duration) -> io::Result<T>
+    where
+        T: Clone + 'static,
+        F: FnMut(&T) -> bool,
+    {
+        self.announce();
+        let wait = async {
+            loop {
+                let (raw, packet) = self.read_packet().await?;
+                if let Some(found) = raw.as_packet::<T>().filter(|p| predicate(p)) {
+                    return Ok(found.clone());
+                }
+                if let Some(reason) = packet.as_ref().and_then(disconnect_reason) {
+                    self.handle.publish(EventKind::Disconnected {
+                        reason: Some(reason.clone()),
+                    });
+                    return Err(disconnected_error(&reason));
+                }
             }
         };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| wait_timed_out::<T>(timeout))?
+    }
 
-        if let Err(e) = packet.write_to_boxed(&mut *r).await {
-            eprintln!("Failed to send packet: {:?}", e);
-        }
+    /// Отправляет пакет и ждёт ответ типа `T`
+    pub async fn request<P, T>(&mut self, packet: &P, timeout: Duration) -> io::Result<T>
+    where
+        P: AsyncPacket,
+        T: Clone + 'static,
+    {
+        self.handle.send_packet(packet).await?;
+        self.wait_for::<T>(timeout).await
     }
 
+    /// `ClientStatus` «запросить статистику» и ответный `Statistics`
+    pub async fn request_statistics(&mut self, timeout: Duration) -> io::Result<Statistics> {
+        self.request(&statistics_request(), timeout).await
+    }
 
-    /// Включение шифрования:
-    /// - Берём старый Plain-стрим (ConnReader::Plain)
-    /// - Создаём зашифрованный EncryptedStream
-    /// - Кладём обратно как ConnReader::Encrypted(...)
-    pub fn enable_encryption(&mut self, key: &[u8]) -> io::Result<()> {
-        let old = self.reader.take().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "No connection reader to upgrade")
-        })?;
+    /// Варианты дополнения для `text` (команда или ник в чате)
+    pub async fn tab_complete(&mut self, text: &str, timeout: Duration) -> io::Result<Vec<String>> {
+        let response: STabComplete = self.request(&tab_complete_request(text), timeout).await?;
+        Ok(response.into_matches())
+    }
 
-        match old {
-            ConnReader::Plain(plain_buf) => {
-                let tcp = plain_buf.into_inner();
-                let encrypted = crate::protocol::crypto::EncryptedStream::new(tcp, key)?;
-                let buf_enc = BufReader::new(encrypted);
-                self.reader = Some(ConnReader::Encrypted(buf_enc));
+    /// Читает и декодирует следующий пакет, выполняет протокольные обязанности
+    /// и публикует его подписчикам. Второй элемент — `None` для пакета, которого нет в `ServerPacket`.
+    pub(crate) async fn read_packet(&mut self) -> io::Result<(Arc<dyn AsyncPacket + Send>, Option<ServerPacket>)> {
+        let frame = self.next_frame().await?;
+        let state = self.state();
+        let boxed_packet: Arc<dyn AsyncPacket + Send> =
+            decode_server_packet(&frame, state).await?.into();
+        let Some(packet) = ServerPacket::try_from(&*boxed_packet) else {
+            // Пакет без модели: ядру и обработчику в нём делать нечего
+            self.handle.publish(EventKind::Packet(boxed_packet.clone()));
+            return Ok((boxed_packet, None));
+        };
+
+        self.handle_core(&packet).await?;
+        self.handle.publish(EventKind::Packet(boxed_packet.clone()));
+        Ok((boxed_packet, Some(packet)))
+    }
+
+    async fn next_frame(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(frame) = take_frame(&mut self.inbound)? {
+                return Ok(frame);
             }
-            ConnReader::Encrypted(_) => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Already encrypted",
-                ));
+            let r = self
+                .reader
+                .as_mut()
+                .ok_or_else(|| io::Error::other("No connection reader available"))?;
+            if r.read_buf(&mut self.inbound).await? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "End of stream"));
             }
         }
+    }
+
+    /// Хост и порт из адреса, переданного в `connect`
+    pub(crate) fn target(&self) -> (String, u16) {
+        split_address(&self.addr)
+    }
+
+    pub(crate) fn announce(&mut self) {
+        if self.announce_pending {
+            self.announce_pending = false;
+            self.handle.publish(EventKind::Connected {
+                peer: self.peer.clone(),
+            });
+        }
+    }
+
+    pub async fn send_packet<P>(&self, packet: &P)
+    where
+        P: AsyncPacket,
+    {
+        if let Err(e) = self.handle.send_packet(packet).await {
+            eprintln!("Failed to send packet: {:?}", e);
+        }
+    }
+
+    /// Включение шифрования сразу для обеих половин:
+    /// - читающая переключается здесь же, до чтения следующего пакета
+    /// - пишущая — когда задача-писатель дойдёт до команды в очереди,
+    ///   то есть всё отправленное раньше уйдёт открытым текстом
+    pub async fn enable_encryption(&mut self, key: &[u8; 16]) -> io::Result<()> {
+        if !self.inbound.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Unencrypted data buffered while enabling encryption",
+            ));
+        }
+        let old = self
+            .reader
+            .take()
+            .ok_or_else(|| io::Error::other("No connection reader to upgrade"))?;
+        self.reader = Some(old.into_encrypted(key)?);
+        self.handle.enable_encryption(*key).await?;
+        self.handle.publish(EventKind::Encrypted);
         Ok(())
     }
 }
+
+fn disconnect_reason(packet: &ServerPacket) -> Option<String> {
+    match packet {
+        ServerPacket::LoginDisconnect(p) => Some(p.reason.0.clone()),
+        ServerPacket::Disconnect(p) => Some(p.reason.0.clone()),
+        _ => None,
+    }
+}
+
+pub(crate) fn disconnected_error(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        format!("Disconnected by server: {}", chat::render_json(reason, Language::default())),
+    )
+}
+
+pub(crate) fn wait_timed_out<T>(timeout: Duration) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!(
+            "Timed out after {:?} waiting for {}",
+            timeout,
+            std::any::type_name::<T>()
+        ),
+    )
+}
+
+pub(crate) fn statistics_request() -> ClientStatus {
+    ClientStatus {
+        action_id: VarInt(CLIENT_STATUS_REQUEST_STATS),
+    }
+}
+
+pub(crate) fn tab_complete_request(text: &str) -> TabComplete {
+    TabComplete {
+        text: VarString(text.to_string()),
+    }
+}
@@ -0,0 +1,210 @@
+use crate::connection::channels::{join_channels, PluginMessage, REGISTER_CHANNEL, UNREGISTER_CHANNEL};
+use crate::connection::conn_writer::Outbound;
+use crate::connection::connection_state::ConnectionState;
+use crate::connection::connection::{statistics_request, tab_complete_request};
+use crate::connection::events::{wait_on_events, ConnectionEvent, EventKind, EVENT_QUEUE_SIZE};
+use crate::protocol::fml::multipart::{self, MAX_PAYLOAD_SIZE};
+use crate::protocol::fml::{IdRegistry, FML_MULTIPART_CHANNEL};
+use crate::protocol::channels::minecraft::{self, BOOK_EDIT_CHANNEL, BOOK_SIGN_CHANNEL, BRAND_CHANNEL, ITEM_NAME_CHANNEL, TRADE_SELECT_CHANNEL};
+use crate::protocol::fields::{ByteArrayShort, VarString};
+use crate::protocol::packets::{client, AsyncPacket, STabComplete, Statistics};
+use crate::world::{EntityTracker, World};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io;
+use tokio::sync::{broadcast, mpsc};
+
+/// Дешёвый клонируемый доступ к соединению: отправка пакетов из любой задачи,
+/// пока `Connection::run` читает входящие.
+#[derive(Clone)]
+pub struct ConnectionHandle {
+    outbound: mpsc::Sender<Outbound>,
+    state: Arc<Mutex<ConnectionState>>,
+    events: broadcast::Sender<ConnectionEvent>,
+    registry: Arc<Mutex<Arc<IdRegistry>>>,
+    world: Arc<Mutex<World>>,
+    entities: Arc<Mutex<EntityTracker>>,
+}
+
+impl ConnectionHandle {
+    pub(crate) fn new(outbound: mpsc::Sender<Outbound>) -> Self {
+        Self {
+            outbound,
+            state: Arc::new(Mutex::new(ConnectionState::Handshaking)),
+            events: broadcast::channel(EVENT_QUEUE_SIZE).0,
+            registry: Arc::default(),
+            world: Arc::default(),
+            entities: Arc::default(),
+        }
+    }
+
+    /// Подписка на события соединения. Подписчик видит только события после подписки.
+    pub fn subscribe(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Отправитель событий для задач, которым нельзя держать `ConnectionHandle`:
+    /// писатель, держащий ручку, держал бы открытой и свою же очередь
+    pub(crate) fn event_sender(&self) -> broadcast::Sender<ConnectionEvent> {
+        self.events.clone()
+    }
+
+    /// Публикует событие с текущим временем. Отсутствие подписчиков — не ошибка.
+    pub(crate) fn publish(&self, kind: EventKind) {
+        let _ = self.events.send(ConnectionEvent::now(kind));
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        *self.state.lock().unwrap()
+    }
+
+    pub fn set_state(&self, state: ConnectionState) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    /// Таблица id блоков и предметов сервера. Пустая, пока FML не прислал `ModIdData`.
+    pub fn registry(&self) -> Arc<IdRegistry> {
+        self.registry.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_registry(&self, registry: IdRegistry) {
+        *self.registry.lock().unwrap() = Arc::new(registry);
+    }
+
+    /// Мир вокруг бота. Блокировку нельзя держать через `.await`:
+    /// пока она взята, цикл чтения не может применить следующий чанк.
+    pub fn world(&self) -> MutexGuard<'_, World> {
+        self.world.lock().unwrap()
+    }
+
+    /// Сущности вокруг бота. Как и с миром, блокировку нельзя держать через `.await`.
+    pub fn entities(&self) -> MutexGuard<'_, EntityTracker> {
+        self.entities.lock().unwrap()
+    }
+
+    /// Сериализует пакет и ставит его в исходящую очередь.
+    pub async fn send_packet<P>(&self, packet: &P) -> io::Result<()>
+    where
+        P: AsyncPacket,
+    {
+        let mut buf = Vec::new();
+        packet.write_to_boxed(&mut buf).await?;
+        self.send_command(Outbound::Packet(buf)).await
+    }
+
+    /// Сообщение в канал плагина (`CustomPayload`). Не влезающее в один пакет
+    /// уходит частями через `FML|MP` — собрать его сможет только сервер с Forge.
+    pub async fn send_plugin_message(&self, channel: &str, data: &[u8]) -> io::Result<()> {
+        if data.len() <= MAX_PAYLOAD_SIZE {
+            return self.send_payload(channel, data).await;
+        }
+        for part in multipart::split(channel, data).await? {
+            self.send_payload(FML_MULTIPART_CHANNEL, &part).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_payload(&self, channel: &str, data: &[u8]) -> io::Result<()> {
+        let payload = client::CustomPayload {
+            channel: VarString(channel.to_string()),
+            data: ByteArrayShort(data.to_vec()),
+        };
+        self.send_packet(&payload).await
+    }
+
+    /// Объявляет серверу каналы, которые клиент готов принимать
+    pub async fn register_channels<'a>(&self, channels: impl IntoIterator<Item = &'a str>) -> io::Result<()> {
+        self.send_plugin_message(REGISTER_CHANNEL, &join_channels(channels)).await
+    }
+
+    pub async fn unregister_channels<'a>(&self, channels: impl IntoIterator<Item = &'a str>) -> io::Result<()> {
+        self.send_plugin_message(UNREGISTER_CHANNEL, &join_channels(channels)).await
+    }
+
+    /// Типизированное сообщение в канал плагина, например
+    /// `send_message(BUNGEE_CHANNEL, &BungeeRequest::GetServers)`
+    pub async fn send_message<M: PluginMessage + Sync>(&self, channel: &str, message: &M) -> io::Result<()> {
+        self.send_plugin_message(channel, &message.encode().await?).await
+    }
+
+    /// `MC|Brand`: чем клиент представляется серверу
+    pub async fn send_brand(&self, brand: &str) -> io::Result<()> {
+        self.send_plugin_message(BRAND_CHANNEL, brand.as_bytes()).await
+    }
+
+    /// `MC|TrSel`: выбрать сделку в открытом окне торговли
+    pub async fn select_trade(&self, index: i32) -> io::Result<()> {
+        self.send_plugin_message(TRADE_SELECT_CHANNEL, &minecraft::trade_select(index).await?).await
+    }
+
+    /// `MC|ItemName`: имя предмета в открытой наковальне
+    pub async fn rename_item(&self, name: &str) -> io::Result<()> {
+        self.send_plugin_message(ITEM_NAME_CHANNEL, name.as_bytes()).await
+    }
+
+    /// `MC|BEdit`: сохранить страницы книги с пером в руке
+    pub async fn edit_book(&self, pages: &[String]) -> io::Result<()> {
+        self.send_plugin_message(BOOK_EDIT_CHANNEL, &minecraft::book_edit(pages).await?).await
+    }
+
+    /// `MC|BSign`: подписать книгу с пером в руке
+    pub async fn sign_book(&self, title: &str, author: &str, pages: &[String]) -> io::Result<()> {
+        self.send_plugin_message(BOOK_SIGN_CHANNEL, &minecraft::book_sign(title, author, pages).await?)
+            .await
+    }
+
+    /// Ждёт следующий пакет типа `T` из цикла `Connection::run`, запущенного в другой задаче.
+    pub async fn wait_for<T>(&self, timeout: Duration) -> io::Result<T>
+    where
+        T: Clone + 'static,
+    {
+        self.wait_for_matching(|_: &T| true, timeout).await
+    }
+
+    pub async fn wait_for_matching<T, F>(&self, predicate: F, timeout: Duration) -> io::Result<T>
+    where
+        T: Clone + 'static,
+        F: FnMut(&T) -> bool,
+    {
+        let mut events = self.subscribe();
+        wait_on_events(&mut events, predicate, timeout).await
+    }
+
+    /// Отправляет пакет и ждёт ответ типа `T`. Подписка оформляется до отправки,
+    /// так что быстрый ответ не теряется.
+    pub async fn request<P, T>(&self, packet: &P, timeout: Duration) -> io::Result<T>
+    where
+        P: AsyncPacket,
+        T: Clone + 'static,
+    {
+        let mut events = self.subscribe();
+        self.send_packet(packet).await?;
+        wait_on_events(&mut events, |_: &T| true, timeout).await
+    }
+
+    pub async fn request_statistics(&self, timeout: Duration) -> io::Result<Statistics> {
+        self.request(&statistics_request(), timeout).await
+    }
+
+    pub async fn tab_complete(&self, text: &str, timeout: Duration) -> io::Result<Vec<String>> {
+        let response: STabComplete = self.request(&tab_complete_request(text), timeout).await?;
+        Ok(response.into_matches())
+    }
+
+    /// Пакеты, поставленные в очередь после этого вызова, шифруются.
+    /// Читающую половину переключает сам `Connection`.
+    pub(crate) async fn enable_encryption(&self, key: [u8; 16]) -> io::Result<()> {
+        self.send_command(Outbound::EnableEncryption(key)).await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.outbound.is_closed()
+    }
+
+    async fn send_command(&self, cmd: Outbound) -> io::Result<()> {
+        self.outbound
+            .send(cmd)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Connection writer is closed"))
+    }
+}
@@ -1,265 +1,577 @@
-use crate::auth::join_auth_server;
+use crate::auth::server_hash;
+use crate::connection::channels::{REGISTER_CHANNEL, UNREGISTER_CHANNEL};
 use crate::connection::connection::Connection;
 use crate::connection::connection_state::ConnectionState;
+use crate::connection::events::EventKind;
 use crate::protocol::crypto::{encrypt_with_server_pubkey, generate_shared_secret};
-use crate::protocol::fields::{ByteArrayShort, VarString};
+use crate::protocol::fields::{Boolean, ByteArrayShort, Double, Float};
+use crate::protocol::fml::{FmlMessage, FmlReply, IdRegistry, FML_HANDSHAKE_CHANNEL, FML_MULTIPART_CHANNEL};
 use crate::protocol::packets::*;
-
-impl ServerPacketHandler for Connection {
-    async fn handle_login_disconnect(&mut self, packet: LoginDisconnect) {
-        println!("Got login disconnect packet: {:?}", packet);
-    }
-
-    async fn handle_encryption_request(&mut self, packet: EncryptionRequest) {
-        let server_id_str = &packet.server_id.0; 
-        let shared_secret = generate_shared_secret();
-        let encrypted_secret =
-            encrypt_with_server_pubkey(&shared_secret, &packet.public_key.0).unwrap();
-        let encrypted_token =
-            encrypt_with_server_pubkey(&packet.verify_token.0, &packet.public_key.0).unwrap();
-
-        let response = EncryptionResponse {
-            shared_secret: ByteArrayShort(encrypted_secret),
-            verify_token: ByteArrayShort(encrypted_token),
-        };
-        self.send_packet(&response).await;
-
-        self.enable_encryption(&shared_secret).unwrap();
-        println!("Encrypted!!!");
-
-        let access_token = "";
-        let selected_profile = "";
-
-        match join_auth_server(
-            server_id_str,
-            &shared_secret,
-            &packet.public_key.0,
-            access_token,
-            selected_profile,
-        ).await
-        {
-            Ok(_) => println!("Successfully joined auth server!"),
-            Err(e) => eprintln!("Failed to join auth server: {}", e),
+use tokio::io;
+
+/// Высота глаз игрока: сервер присылает в `PlayerPositionAndLook` именно её
+const PLAYER_EYE_HEIGHT: f64 = 1.62;
+
+/// Протокольные обязанности соединения. Выполняются до того, как пакет увидит
+/// пользовательский обработчик, и не зависят от него.
+impl Connection {
+    pub(crate) async fn handle_core(&mut self, packet: &ServerPacket) -> io::Result<()> {
+        self.update_world(packet);
+        self.update_entities(packet);
+        match packet {
+            ServerPacket::EncryptionRequest(packet) => self.handle_encryption_request(packet).await,
+            ServerPacket::LoginSuccess(packet) => {
+                self.set_state(ConnectionState::Play);
+                self.handle().publish(EventKind::LoggedIn {
+                    uuid: packet.uuid.0.clone(),
+                    username: packet.username.0.clone(),
+                });
+                let channels = self.channels.channels();
+                if channels.is_empty() {
+                    return Ok(());
+                }
+                self.handle().register_channels(channels.iter().map(String::as_str)).await
+            }
+            ServerPacket::KeepAlive(packet) => {
+                let c_keep_alive = client::KeepAlive {
+                    keep_alive_id: packet.keep_alive_id.clone(),
+                };
+                self.handle().send_packet(&c_keep_alive).await
+            }
+            ServerPacket::JoinGame(packet) => {
+                self.entity_id = Some(packet.entity_id.0);
+                match &self.brand {
+                    Some(brand) => self.handle().send_brand(brand).await,
+                    None => Ok(()),
+                }
+            }
+            ServerPacket::PlayerPositionAndLook(packet) => {
+                // Клиент обязан подтвердить телепорт, иначе сервер не примет его движения
+                let confirm = PlayerPosLook {
+                    x: Double(packet.x.0),
+                    y: Double(packet.y.0 - PLAYER_EYE_HEIGHT),
+                    stance: Double(packet.y.0),
+                    z: Double(packet.z.0),
+                    yaw: Float(packet.yaw.0),
+                    pitch: Float(packet.pitch.0),
+                    on_ground: Boolean(packet.on_ground.0),
+                };
+                self.handle().send_packet(&confirm).await
+            }
+            ServerPacket::CustomPayload(packet) => {
+                self.handle_plugin_message(&packet.channel.0, &packet.data.0).await
+            }
+            _ => Ok(()),
         }
     }
 
-    async fn handle_login_success(&mut self, packet: LoginSuccess) {
-        self.state = ConnectionState::Play;
-        println!(
-            "Login success! username: {:?}, uuid: {:?}",
-            &packet.username, &packet.uuid
-        );
-    }
-
-    async fn handle_keep_alive(&mut self, packet: server::KeepAlive) {
-        println!("Received keepalive");
-        let c_keep_alive = client::KeepAlive {
-            keep_alive_id: packet.keep_alive_id,
-        };
-        self.send_packet(&c_keep_alive).await;
-    }
-
-    async fn handle_join_game(&mut self, packet: JoinGame) {
-        println!("Got joined game packet {:?}", &packet);
-    }
-
-    async fn handle_s_chat_message(&mut self, _packet: SChatMessage) {
-        todo!()
-    }
-
-    async fn handle_time_update(&mut self, _packet: TimeUpdate) {
-        todo!()
-    }
-
-    async fn handle_entity_equipment(&mut self, _packet: EntityEquipment) {
-        todo!()
-    }
-
-    async fn handle_spawn_position(&mut self, _packet: SpawnPosition) {
-        todo!()
-    }
-
-    async fn handle_update_health(&mut self, _packet: UpdateHealth) {
-        todo!()
-    }
-
-    async fn handle_respawn(&mut self, _packet: Respawn) {
-        todo!()
-    }
-
-    async fn handle_player_position_and_look(&mut self, _packet: PlayerPositionAndLook) {
-        todo!()
-    }
-
-    async fn handle_held_item_change(&mut self, _packet: HeldItemChange) {
-        todo!()
-    }
-
-    async fn handle_use_bed(&mut self, _packet: UseBed) {
-        todo!()
-    }
-
-    async fn handle_animation(&mut self, _packet: Animation) {
-        todo!()
-    }
-
-    async fn handle_spawn_player(&mut self, _packet: SpawnPlayer) {
-        todo!()
-    }
-
-    async fn handle_collect_item(&mut self, _packet: CollectItem) {
-        todo!()
-    }
-
-    async fn handle_spawn_object(&mut self, _packet: SpawnObject) {
-        todo!()
-    }
-
-    async fn handle_spawn_mob(&mut self, _packet: SpawnMob) {
-        todo!()
-    }
-
-    async fn handle_spawn_painting(&mut self, _packet: SpawnPainting) {
-        todo!()
-    }
-
-    async fn handle_spawn_experience_orb(&mut self, _packet: SpawnExperienceOrb) {
-        todo!()
-    }
-
-    async fn handle_entity_velocity(&mut self, _packet: EntityVelocity) {
-        todo!()
-    }
-
-    async fn handle_destroy_entities(&mut self, _packet: DestroyEntities) {
-        todo!()
-    }
-
-    async fn handle_entity(&mut self, _packet: Entity) {
-        todo!()
-    }
-
-    async fn handle_entity_rel_move(&mut self, _packet: EntityRelMove) {
-        todo!()
-    }
-
-    async fn handle_entity_look_and_movement(&mut self, _packet: EntityLookAndMovement) {
-        todo!()
-    }
-
-    async fn handle_entity_look_move(&mut self, _packet: EntityLookMove) {
-        todo!()
-    }
-
-    async fn handle_entity_teleport(&mut self, _packet: EntityTeleport) {
-        todo!()
-    }
-
-    async fn handle_entity_status(&mut self, _packet: EntityStatus) {
-        todo!()
-    }
-
-    async fn handle_attach_entity(&mut self, _packet: AttachEntity) {
-        todo!()
-    }
-
-    async fn handle_entity_metadata(&mut self, _packet: EntityMetadata) {
-        todo!()
-    }
-
-    async fn handle_entity_effect(&mut self, _packet: EntityEffect) {
-        todo!()
-    }
-
-    async fn handle_remove_entity_effect(&mut self, _packet: RemoveEntityEffect) {
-        todo!()
-    }
-
-    async fn handle_experience(&mut self, _packet: Experience) {
-        todo!()
-    }
-
-    async fn handle_set_experience(&mut self, _packet: SetExperience) {
-        todo!()
-    }
-
-    async fn handle_entity_properties(&mut self, _packet: EntityProperties) {
-        todo!()
-    }
-
-    async fn handle_chunk_data(&mut self, _packet: ChunkData) {
-        todo!()
-    }
-
-    async fn handle_multi_block_change(&mut self, _packet: MultiBlockChange) {
-        todo!()
+    fn update_world(&mut self, packet: &ServerPacket) {
+        let handle = self.handle();
+        let applied = handle.world().apply(packet);
+        match applied {
+            Ok(changes) => {
+                for change in changes {
+                    handle.publish(EventKind::World(change));
+                }
+            }
+            // Битый чанк не повод рвать соединение: мир просто пропускает пакет
+            Err(e) => handle.publish(EventKind::from(&e)),
+        }
     }
 
-    async fn handle_block_change(&mut self, _packet: BlockChange) {
-        todo!()
+    fn update_entities(&mut self, packet: &ServerPacket) {
+        let handle = self.handle();
+        let changes = handle.entities().apply(packet);
+        for change in changes {
+            handle.publish(EventKind::Entity(change));
+        }
     }
 
-    async fn handle_map_chunk_bulk(&mut self, _packet: MapChunkBulk) {
-        todo!()
+    async fn handle_plugin_message(&mut self, channel: &str, data: &[u8]) -> io::Result<()> {
+        if channel == FML_MULTIPART_CHANNEL {
+            // Собранное сообщение обрабатывается так, будто пришло в свой канал целиком
+            return match self.multipart.accept(data).await? {
+                Some((channel, data)) => self.route_plugin_message(&channel, &data).await,
+                None => Ok(()),
+            };
+        }
+        self.route_plugin_message(channel, data).await
+    }
+
+    async fn route_plugin_message(&mut self, channel: &str, data: &[u8]) -> io::Result<()> {
+        match channel {
+            REGISTER_CHANNEL => self.channels.server_registered(data),
+            UNREGISTER_CHANNEL => self.channels.server_unregistered(data),
+            FML_HANDSHAKE_CHANNEL => return self.handle_fml_handshake(data).await,
+            _ => {
+                // Ошибка обработчика канала или разбора его сообщения не рвёт соединение
+                let handle = self.handle();
+                if let Err(e) = self.channels.dispatch(&handle, channel, data).await {
+                    handle.publish(EventKind::from(&e));
+                }
+            }
+        }
+        Ok(())
     }
 
-    async fn handle_explosion(&mut self, _packet: Explosion) {
-        todo!()
+    async fn handle_fml_handshake(&mut self, data: &[u8]) -> io::Result<()> {
+        let message = FmlMessage::decode(data).await?;
+        let registry = match &message {
+            FmlMessage::ModIdData(data) => Some(IdRegistry::from_mod_id_data(data)),
+            _ => None,
+        };
+        let replies = self.fml.accept(message)?;
+        if let Some(registry) = registry {
+            self.handle().set_registry(registry);
+        }
+        for reply in replies {
+            match reply {
+                FmlReply::Register(mut channels) => {
+                    // Каналы модов бота объявляются вместе с каналами FML
+                    channels.extend(self.channels.channels());
+                    self.handle()
+                        .register_channels(channels.iter().map(String::as_str))
+                        .await?
+                }
+                FmlReply::Message(message) => {
+                    self.handle()
+                        .send_plugin_message(FML_HANDSHAKE_CHANNEL, &message.encode().await?)
+                        .await?
+                }
+            }
+        }
+        Ok(())
     }
 
-    async fn handle_effect(&mut self, _packet: Effect) {
-        todo!()
-    }
+    /// Порядок важен: сервер проверяет сессию сразу, как получит `EncryptionResponse`,
+    /// поэтому join должен завершиться до отправки ответа, а шифрование включается после.
+    async fn handle_encryption_request(&mut self, packet: &EncryptionRequest) -> io::Result<()> {
+        let public_key = &packet.public_key.0;
+        let shared_secret = generate_shared_secret();
+        let encrypted_secret = encrypt_with_server_pubkey(&shared_secret, public_key)?;
+        let encrypted_token = encrypt_with_server_pubkey(&packet.verify_token.0, public_key)?;
 
-    async fn handle_sound_effect(&mut self, _packet: SoundEffect) {
-        todo!()
-    }
+        let hash = server_hash(&packet.server_id.0, &shared_secret, public_key);
+        self.auth.join(&self.session, &hash).await?;
 
-    async fn handle_change_game_state(&mut self, _packet: ChangeGameState) {
-        todo!()
+        let response = EncryptionResponse {
+            shared_secret: ByteArrayShort(encrypted_secret),
+            verify_token: ByteArrayShort(encrypted_token),
+        };
+        self.handle().send_packet(&response).await?;
+        self.enable_encryption(&shared_secret).await
     }
+}
 
-    async fn handle_close_window(&mut self, _packet: CloseWindow) {
-        todo!()
+#[cfg(test)]
+mod tests {
+    use crate::auth::session_server::has_joined;
+    use crate::auth::yggdrasil::Profile;
+    use crate::auth::{http, server_hash, LauncherBackend, SessionServer};
+    use crate::connection::channels::{split_channels, REGISTER_CHANNEL};
+    use crate::connection::connection::Connection;
+    use crate::connection::connection_handle::ConnectionHandle;
+    use crate::connection::connection_state::ConnectionState;
+    use crate::connection::default_handler::NoopHandler;
+    use crate::connection::events::EventKind;
+    use crate::connection::incoming::IncomingConnection;
+    use crate::connection::login::{LoginOptions, LoginOutcome, SessionCredentials};
+    use crate::protocol::crypto::ServerKeyPair;
+    use crate::protocol::fields::{Boolean, ByteArrayInt, ByteArrayVarShort, Int, UShort, VarString};
+    use crate::protocol::fml::{
+        multipart, FmlClientState, FmlMessage, FmlMod, ModIdData, MultipartAssembler, FML_CHANNELS,
+        FML_HANDSHAKE_CHANNEL, FML_MULTIPART_CHANNEL,
+    };
+    use crate::protocol::packets::client;
+    use crate::protocol::packets::server::{ChunkData, CustomPayload, Disconnect, LoginSuccess};
+    use crate::protocol::packets::{Handshake, LoginStart};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::DuplexStream;
+    use tokio::net::TcpListener;
+
+    const SERVER_ID: &str = "";
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    /// Сессионный сервер: пишет "join" в лог, запоминает тело и отвечает `status`
+    async fn mock_session_server(status: u16, log: Log, bodies: Arc<Mutex<Vec<String>>>) -> String {
+        let base_url = http::serve_local(move |request| {
+            log.lock().unwrap().push("join");
+            bodies.lock().unwrap().push(request.body);
+            (status, String::new())
+        })
+        .await;
+        format!("{}/join", base_url)
+    }
+
+    /// Игровой сервер в online-режиме: просит шифрование, проверяет ответ
+    /// и отправляет зашифрованный `LoginSuccess`. Возвращает общий секрет и ключ сервера.
+    async fn mock_game_server(log: Log) -> (String, tokio::task::JoinHandle<std::io::Result<([u8; 16], Vec<u8>)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let task = tokio::spawn(async move {
+            let keys = ServerKeyPair::generate()?;
+            let mut client = IncomingConnection::accept(&listener).await?;
+            client.expect::<Handshake>().await?;
+            client.expect::<LoginStart>().await?;
+
+            let secret = client.negotiate_encryption(&keys, SERVER_ID).await?;
+            log.lock().unwrap().push("response");
+
+            let success = LoginSuccess {
+                uuid: VarString("00000000-0000-0000-0000-000000000001".to_string()),
+                username: VarString("tester".to_string()),
+            };
+            client.send_packet(&success).await?;
+            Ok((secret, keys.public_key_der().to_vec()))
+        });
+        (addr, task)
+    }
+
+    /// Серверная сторона offline-логина: принимает `Handshake` и `LoginStart`,
+    /// отвечает `LoginSuccess` и переходит в Play
+    async fn mock_login(server_io: DuplexStream) -> std::io::Result<IncomingConnection<DuplexStream>> {
+        let mut client = IncomingConnection::new(server_io);
+        client.expect::<Handshake>().await?;
+        client.expect::<LoginStart>().await?;
+        let success = LoginSuccess {
+            uuid: VarString("00000000-0000-0000-0000-000000000001".to_string()),
+            username: VarString("tester".to_string()),
+        };
+        client.send_packet(&success).await?;
+        client.set_state(ConnectionState::Play);
+        Ok(client)
     }
 
-    async fn handle_set_slot(&mut self, _packet: SetSlot) {
-        todo!()
-    }
+    fn options(join_url: String) -> LoginOptions {
+        let mut options = LoginOptions::new("tester");
+        options.session = SessionCredentials {
+            access_token: "token".to_string(),
+            selected_profile: "profile".to_string(),
+        };
+        options.auth = Arc::new(LauncherBackend::new(&join_url));
+        options.timeout = Duration::from_secs(20);
+        options
+    }
+
+    #[tokio::test]
+    async fn session_join_happens_before_encryption_response() {
+        let log = Log::default();
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let join_url = mock_session_server(204, log.clone(), bodies.clone()).await;
+        let (addr, server) = mock_game_server(log.clone()).await;
+
+        let mut conn = Connection::connect(&addr).await.unwrap();
+        let outcome = conn.login(options(join_url)).await.unwrap();
+        assert_eq!(
+            outcome,
+            LoginOutcome::Success {
+                uuid: "00000000-0000-0000-0000-000000000001".to_string(),
+                username: "tester".to_string(),
+            }
+        );
+        assert_eq!(*log.lock().unwrap(), ["join", "response"]);
 
-    async fn handle_window_items(&mut self, _packet: WindowItems) {
-        todo!()
+        let (secret, public_der) = server.await.unwrap().unwrap();
+        let body: serde_json::Value = serde_json::from_str(&bodies.lock().unwrap()[0]).unwrap();
+        assert_eq!(body["accessToken"], "token");
+        assert_eq!(body["selectedProfile"], "profile");
+        assert_eq!(body["serverId"], server_hash(SERVER_ID, &secret, &public_der));
     }
 
-    async fn handle_update_tile_entity(&mut self, _packet: UpdateTileEntity) {
-        todo!()
-    }
+    #[tokio::test]
+    async fn failed_session_join_aborts_without_response() {
+        let log = Log::default();
+        let join_url = mock_session_server(403, log.clone(), Arc::default()).await;
+        let (addr, server) = mock_game_server(log.clone()).await;
 
-    async fn handle_statistics(&mut self, _packet: Statistics) {
-        todo!()
-    }
+        let mut conn = Connection::connect(&addr).await.unwrap();
+        let err = conn.login(options(join_url)).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        drop(conn);
 
-    async fn handle_player_list_item(&mut self, _packet: PlayerListItem) {
-        todo!()
+        // Ответ так и не отправлен: сервер видит закрытие вместо EncryptionResponse
+        assert!(server.await.unwrap().is_err());
+        assert_eq!(*log.lock().unwrap(), ["join"]);
     }
 
-    async fn handle_player_abilities(&mut self, _packet: PlayerAbilities) {
-        todo!()
-    }
+    #[tokio::test]
+    async fn local_session_server_verifies_join() {
+        let session = SessionServer::bind("127.0.0.1:0").await.unwrap();
+        let profile = Profile {
+            id: "profile".to_string(),
+            name: "tester".to_string(),
+        };
+        session.register("token", profile.clone());
+        let (addr, server) = mock_game_server(Log::default()).await;
+
+        let mut conn = Connection::connect(&addr).await.unwrap();
+        let mut options = options(String::new());
+        options.auth = Arc::new(session.backend());
+        assert!(matches!(conn.login(options).await.unwrap(), LoginOutcome::Success { .. }));
+
+        let (secret, public_der) = server.await.unwrap().unwrap();
+        let hash = server_hash(SERVER_ID, &secret, &public_der);
+        assert_eq!(has_joined(&session.base_url(), "tester", &hash).await.unwrap(), Some(profile.clone()));
+        assert_eq!(session.verify("tester", SERVER_ID, &secret, &public_der), Some(profile));
+    }
+
+    #[tokio::test]
+    async fn fml_handshake_runs_to_done() {
+        let server_mods = vec![FmlMod::new("FML", "7.10.99.99"), FmlMod::new("IC2", "2.2.827")];
+        let registry = ModIdData {
+            ids: vec![("\u{1}IC2:blockMachine".to_string(), 250)],
+            ..ModIdData::default()
+        };
 
-    async fn handle_custom_payload(&mut self, packet: server::CustomPayload) {
-        if packet.channel.0 == "FML|HS" {
-            println!("Received FML handshake payload: {:?}", packet.data.0);
-            let response_channel = VarString("FML|HS".to_string());
-            let response_data = ByteArrayShort(vec![0x00]);
-            let response_packet = client::CustomPayload {
-                channel: response_channel,
-                data: response_data,
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (mods, ids) = (server_mods.clone(), registry.clone());
+        let server = tokio::spawn(async move {
+            let mut client = mock_login(server_io).await?;
+
+            let mut registered = Vec::new();
+            let mut replies = Vec::new();
+            let script = [
+                (FmlMessage::ServerHello { protocol_version: 2, override_dimension: Some(0) }, 3),
+                (FmlMessage::ModList(mods), 1),
+                (FmlMessage::ModIdData(ids), 1),
+                (FmlMessage::HandshakeAck { phase: 2 }, 1),
+                (FmlMessage::HandshakeAck { phase: 3 }, 1),
+            ];
+            for (message, expected_replies) in script {
+                let payload = CustomPayload {
+                    channel: VarString(FML_HANDSHAKE_CHANNEL.to_string()),
+                    data: ByteArrayVarShort(message.encode().await?),
+                };
+                client.send_packet(&payload).await?;
+                for _ in 0..expected_replies {
+                    let reply: client::CustomPayload = client.expect().await?;
+                    if reply.channel.0 == REGISTER_CHANNEL {
+                        // REGISTER уходит до ClientHello
+                        assert!(replies.is_empty());
+                        registered = split_channels(&reply.data.0);
+                        continue;
+                    }
+                    assert_eq!(reply.channel.0, FML_HANDSHAKE_CHANNEL);
+                    replies.push(FmlMessage::decode(&reply.data.0).await?);
+                }
+            }
+            let bye = Disconnect {
+                reason: VarString("{\"text\":\"bye\"}".to_string()),
             };
-            self.send_packet(&response_packet).await;
-            println!("Sent FML handshake HELLO response");
-        } else {
-            println!("Received custom payload on channel: {:?}", packet.channel.0);
+            client.send_packet(&bye).await?;
+            Ok::<_, std::io::Error>((registered, replies))
+        });
+
+        let mut conn = Connection::from_stream(client_io, "localhost:25565");
+        conn.login(LoginOptions::new("tester")).await.unwrap();
+        conn.run(&mut NoopHandler).await.unwrap();
+
+        let (registered, replies) = server.await.unwrap().unwrap();
+        assert_eq!(registered, FML_CHANNELS);
+        assert!(matches!(replies[0], FmlMessage::ClientHello { protocol_version: 2 }));
+        assert_eq!(replies[1], FmlMessage::ModList(conn.fml().mods.clone()));
+        let phases: Vec<_> = replies[2..]
+            .iter()
+            .map(|m| match m {
+                FmlMessage::HandshakeAck { phase } => *phase,
+                other => panic!("expected ack, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(phases, [2, 3, 4, 5]);
+
+        assert_eq!(conn.fml().state(), FmlClientState::Done);
+        assert_eq!(conn.fml().server_mods.as_ref(), Some(&server_mods));
+        assert_eq!(conn.fml().mod_id_data.as_ref(), Some(&registry));
+        assert_eq!(conn.fml().override_dimension, Some(0));
+        assert_eq!(conn.registry().block_name(250), Some("IC2:blockMachine"));
+    }
+
+    #[tokio::test]
+    async fn plugin_channels_are_registered_and_routed() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut client = mock_login(server_io).await?;
+
+            let register: client::CustomPayload = client.expect().await?;
+            assert_eq!(register.channel.0, REGISTER_CHANNEL);
+            assert_eq!(split_channels(&register.data.0), ["WECUI"]);
+
+            for (channel, data) in [(REGISTER_CHANNEL, &b"BungeeCord\0WECUI"[..]), ("WECUI", b"s|cuboid")] {
+                let payload = CustomPayload {
+                    channel: VarString(channel.to_string()),
+                    data: ByteArrayVarShort(data.to_vec()),
+                };
+                client.send_packet(&payload).await?;
+            }
+            // Обработчик отвечает в тот же канал
+            let echo: client::CustomPayload = client.expect().await?;
+            client.send_packet(&Disconnect {
+                reason: VarString("{\"text\":\"bye\"}".to_string()),
+            })
+            .await?;
+            Ok::<_, std::io::Error>(echo)
+        });
+
+        let mut conn = Connection::from_stream(client_io, "localhost:25565");
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        conn.register_channel("WECUI", move |handle: &ConnectionHandle, data: &[u8]| {
+            seen.lock().unwrap().push(data.to_vec());
+            let handle = handle.clone();
+            tokio::spawn(async move { handle.send_plugin_message("WECUI", b"v|3").await });
+            Ok(())
+        })
+        .await
+        .unwrap();
+        conn.login(LoginOptions::new("tester")).await.unwrap();
+        conn.run(&mut NoopHandler).await.unwrap();
+
+        let echo = server.await.unwrap().unwrap();
+        assert_eq!(echo.channel.0, "WECUI");
+        assert_eq!(echo.data.0, b"v|3");
+        assert_eq!(*received.lock().unwrap(), [b"s|cuboid".to_vec()]);
+        assert!(conn.channels().server_has("BungeeCord"));
+    }
+
+    #[tokio::test]
+    async fn channel_handler_errors_do_not_disconnect() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut client = mock_login(server_io).await?;
+            client.expect::<client::CustomPayload>().await?; // REGISTER
+            for data in [&b"bad"[..], b"ok"] {
+                let payload = CustomPayload {
+                    channel: VarString("WECUI".to_string()),
+                    data: ByteArrayVarShort(data.to_vec()),
+                };
+                client.send_packet(&payload).await?;
+            }
+            client
+                .send_packet(&Disconnect {
+                    reason: VarString("{\"text\":\"bye\"}".to_string()),
+                })
+                .await
+        });
+
+        let mut conn = Connection::from_stream(client_io, "localhost:25565");
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        conn.register_channel("WECUI", move |_: &ConnectionHandle, data: &[u8]| {
+            seen.lock().unwrap().push(data.to_vec());
+            match data {
+                b"bad" => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "bad WECUI message")),
+                _ => Ok(()),
+            }
+        })
+        .await
+        .unwrap();
+        let mut events = conn.subscribe();
+        conn.login(LoginOptions::new("tester")).await.unwrap();
+        conn.run(&mut NoopHandler).await.unwrap();
+        server.await.unwrap().unwrap();
+
+        assert_eq!(*received.lock().unwrap(), [b"bad".to_vec(), b"ok".to_vec()]);
+        let mut errors = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let EventKind::Error { kind, message } = event.kind {
+                errors.push((kind, message));
+            }
         }
+        assert_eq!(errors, [(std::io::ErrorKind::InvalidData, "bad WECUI message".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn broken_chunks_do_not_disconnect() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut client = mock_login(server_io).await?;
+            let chunk = ChunkData {
+                x: Int(0),
+                z: Int(0),
+                ground_up: Boolean(true),
+                primary_bitmap: UShort(1 << 4),
+                add_bitmap: UShort(0),
+                data: ByteArrayInt(b"not zlib".to_vec()),
+            };
+            client.send_packet(&chunk).await?;
+            client
+                .send_packet(&Disconnect {
+                    reason: VarString("{\"text\":\"bye\"}".to_string()),
+                })
+                .await
+        });
+
+        let mut conn = Connection::from_stream(client_io, "localhost:25565");
+        let mut events = conn.subscribe();
+        conn.login(LoginOptions::new("tester")).await.unwrap();
+        conn.run(&mut NoopHandler).await.unwrap();
+        server.await.unwrap().unwrap();
+
+        let mut errors = 0;
+        let mut disconnected = false;
+        while let Ok(event) = events.try_recv() {
+            match event.kind {
+                EventKind::Error { .. } => errors += 1,
+                EventKind::Disconnected { reason } => disconnected = reason.is_some(),
+                _ => {}
+            }
+        }
+        assert_eq!(errors, 1);
+        assert!(disconnected);
+        assert!(!conn.handle().world().is_loaded(0, 0));
+    }
+
+    #[tokio::test]
+    async fn multipart_payloads_are_reassembled_both_ways() {
+        let config: Vec<u8> = (0..80_000).map(|i| (i % 251) as u8).collect();
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let sent = config.clone();
+        let server = tokio::spawn(async move {
+            let mut client = mock_login(server_io).await?;
+            client.expect::<client::CustomPayload>().await?; // REGISTER
+
+            for part in multipart::split("ConfigSync", &sent).await? {
+                let payload = CustomPayload {
+                    channel: VarString(FML_MULTIPART_CHANNEL.to_string()),
+                    data: ByteArrayVarShort(part),
+                };
+                client.send_packet(&payload).await?;
+            }
+
+            let mut assembler = MultipartAssembler::default();
+            let echo = loop {
+                let part: client::CustomPayload = client.expect().await?;
+                assert_eq!(part.channel.0, FML_MULTIPART_CHANNEL);
+                if let Some(message) = assembler.accept(&part.data.0).await? {
+                    break message;
+                }
+            };
+            client.send_packet(&Disconnect {
+                reason: VarString("{\"text\":\"bye\"}".to_string()),
+            })
+            .await?;
+            Ok::<_, std::io::Error>(echo)
+        });
+
+        let mut conn = Connection::from_stream(client_io, "localhost:25565");
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        conn.register_channel("ConfigSync", move |handle: &ConnectionHandle, data: &[u8]| {
+            seen.lock().unwrap().push(data.len());
+            let (handle, data) = (handle.clone(), data.to_vec());
+            tokio::spawn(async move { handle.send_plugin_message("ConfigSync", &data).await });
+            Ok(())
+        })
+        .await
+        .unwrap();
+        conn.login(LoginOptions::new("tester")).await.unwrap();
+        conn.run(&mut NoopHandler).await.unwrap();
+
+        let (channel, echo) = server.await.unwrap().unwrap();
+        assert_eq!(channel, "ConfigSync");
+        assert_eq!(echo, config);
+        assert_eq!(*received.lock().unwrap(), [config.len()]);
     }
 }
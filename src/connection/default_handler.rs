@@ -0,0 +1,152 @@
+use crate::protocol::chat::{self, Language};
+use crate::protocol::packets::*;
+use std::collections::HashMap;
+
+/// Обработчик, который ничего не делает. Протокольные обязанности всё равно
+/// выполняет само соединение, так что с ним бот просто держит соединение.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopHandler;
+
+impl ServerPacketHandler for NoopHandler {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerPosition {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+/// Обработчик по умолчанию: запоминает состояние игрока и мира из пакетов
+/// и причину отключения. Сам ничего не печатает, чат доступен через `ConnectionHandle::subscribe`.
+#[derive(Debug, Default, Clone)]
+pub struct StateTracker {
+    pub language: Language,
+    pub username: Option<String>,
+    pub entity_id: Option<i32>,
+    pub game_mode: Option<u8>,
+    pub dimension: Option<i32>,
+    pub level_type: Option<String>,
+    pub position: Option<PlayerPosition>,
+    pub spawn_position: Option<(i32, i32, i32)>,
+    pub health: Option<f32>,
+    pub food: Option<i16>,
+    pub saturation: Option<f32>,
+    pub held_slot: Option<u8>,
+    pub world_age: i64,
+    pub time_of_day: i64,
+    /// Игроки из таб-листа и их пинг
+    pub players: HashMap<String, i16>,
+    pub disconnect_reason: Option<String>,
+}
+
+impl StateTracker {
+    pub fn new(language: Language) -> Self {
+        Self {
+            language,
+            ..Self::default()
+        }
+    }
+}
+
+impl ServerPacketHandler for StateTracker {
+    async fn handle_login_disconnect(&mut self, packet: LoginDisconnect) {
+        self.disconnect_reason = Some(chat::render_json(&packet.reason, self.language));
+    }
+
+    async fn handle_login_success(&mut self, packet: LoginSuccess) {
+        self.username = Some(packet.username.0);
+    }
+
+    async fn handle_join_game(&mut self, packet: JoinGame) {
+        self.entity_id = Some(packet.entity_id.0);
+        self.game_mode = Some(packet.game_mode.0);
+        self.dimension = Some(packet.dimension.0 as i8 as i32);
+        self.level_type = Some(packet.level_type.0);
+    }
+
+    async fn handle_time_update(&mut self, packet: TimeUpdate) {
+        self.world_age = packet.world_age.0;
+        self.time_of_day = packet.time_of_day.0;
+    }
+
+    async fn handle_spawn_position(&mut self, packet: SpawnPosition) {
+        self.spawn_position = Some((packet.x.0, packet.y.0, packet.z.0));
+    }
+
+    async fn handle_update_health(&mut self, packet: UpdateHealth) {
+        self.health = Some(packet.health.0);
+        self.food = Some(packet.food.0);
+        self.saturation = Some(packet.saturation.0);
+    }
+
+    async fn handle_respawn(&mut self, packet: Respawn) {
+        self.dimension = Some(packet.dimension.0);
+        self.game_mode = Some(packet.game_mode.0);
+        self.level_type = Some(packet.level_type.0);
+    }
+
+    async fn handle_player_position_and_look(&mut self, packet: PlayerPositionAndLook) {
+        self.position = Some(PlayerPosition {
+            x: packet.x.0,
+            y: packet.y.0,
+            z: packet.z.0,
+            yaw: packet.yaw.0,
+            pitch: packet.pitch.0,
+            on_ground: packet.on_ground.0,
+        });
+    }
+
+    async fn handle_held_item_change(&mut self, packet: HeldItemChange) {
+        self.held_slot = Some(packet.slot.0);
+    }
+
+    async fn handle_player_list_item(&mut self, packet: PlayerListItem) {
+        // Второе поле в 1.7.10 — флаг «онлайн», а не режим игры
+        if packet.gamemode.0 != 0 {
+            self.players.insert(packet.username.0, packet.ping.0);
+        } else {
+            self.players.remove(&packet.username.0);
+        }
+    }
+
+    async fn handle_disconnect(&mut self, packet: Disconnect) {
+        self.disconnect_reason = Some(chat::render_json(&packet.reason, self.language));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::fields::{Boolean, Double, Float, Long, Short};
+
+    #[tokio::test]
+    async fn tracks_health_position_and_time() {
+        let mut tracker = StateTracker::default();
+        ServerPacket::update_health(Float(15.5), Short(18), Float(2.0))
+            .handle_by(&mut tracker)
+            .await;
+        ServerPacket::player_position_and_look(Double(1.5), Double(65.62), Double(-3.0), Float(90.0), Float(10.0), Boolean(true))
+            .handle_by(&mut tracker)
+            .await;
+        ServerPacket::time_update(Long(1200), Long(6000))
+            .handle_by(&mut tracker)
+            .await;
+
+        assert_eq!((tracker.health, tracker.food, tracker.saturation), (Some(15.5), Some(18), Some(2.0)));
+        assert_eq!(
+            tracker.position,
+            Some(PlayerPosition {
+                x: 1.5,
+                y: 65.62,
+                z: -3.0,
+                yaw: 90.0,
+                pitch: 10.0,
+                on_ground: true,
+            })
+        );
+        assert_eq!((tracker.world_age, tracker.time_of_day), (1200, 6000));
+    }
+}
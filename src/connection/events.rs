@@ -0,0 +1,126 @@
+use crate::protocol::packets::{AsyncPacket, AsyncPacketExt};
+use crate::world::{EntityChange, WorldChange};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use crate::connection::connection::{disconnected_error, wait_timed_out};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io;
+use tokio::sync::broadcast;
+
+/// Сколько событий может накопиться у медленного подписчика, прежде чем он получит `Lagged`
+pub const EVENT_QUEUE_SIZE: usize = 1024;
+
+/// Событие соединения с моментом, когда оно произошло.
+#[derive(Debug, Clone)]
+pub struct ConnectionEvent {
+    pub at: DateTime<Utc>,
+    pub kind: EventKind,
+}
+
+impl ConnectionEvent {
+    pub fn now(kind: EventKind) -> Self {
+        Self {
+            at: Utc::now(),
+            kind,
+        }
+    }
+
+    /// Пакет конкретного типа, если это событие о пакете
+    pub fn packet<T: 'static>(&self) -> Option<&T> {
+        match &self.kind {
+            EventKind::Packet(packet) => packet.as_packet::<T>(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum EventKind {
+    /// Чтение соединения началось (публикуется один раз, когда стартует цикл чтения)
+    Connected { peer: String },
+    /// Обе половины переключились на шифрование
+    Encrypted,
+    LoggedIn { uuid: String, username: String },
+    /// Каждый декодированный пакет, до того как его увидит обработчик
+    Packet(Arc<dyn AsyncPacket + Send>),
+    /// Сервер отключил нас (`reason` — JSON-компонент из пакета) или поток оборвался (`None`)
+    Disconnected { reason: Option<String> },
+    /// Изменение мира, см. `ConnectionHandle::world`
+    World(WorldChange),
+    /// Появление и удаление сущностей, см. `ConnectionHandle::entities`
+    Entity(EntityChange),
+    Error { kind: io::ErrorKind, message: String },
+}
+
+impl fmt::Debug for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::Connected { peer } => f.debug_struct("Connected").field("peer", peer).finish(),
+            EventKind::Encrypted => f.write_str("Encrypted"),
+            EventKind::LoggedIn { uuid, username } => f
+                .debug_struct("LoggedIn")
+                .field("uuid", uuid)
+                .field("username", username)
+                .finish(),
+            EventKind::Packet(packet) => f
+                .debug_struct("Packet")
+                .field("id", &format_args!("0x{:02X}", packet.get_id()))
+                .field("state", &packet.get_state())
+                .finish(),
+            EventKind::Disconnected { reason } => f
+                .debug_struct("Disconnected")
+                .field("reason", reason)
+                .finish(),
+            EventKind::World(change) => f.debug_tuple("World").field(change).finish(),
+            EventKind::Entity(change) => f.debug_tuple("Entity").field(change).finish(),
+            EventKind::Error { kind, message } => f
+                .debug_struct("Error")
+                .field("kind", kind)
+                .field("message", message)
+                .finish(),
+        }
+    }
+}
+
+impl From<&io::Error> for EventKind {
+    fn from(e: &io::Error) -> Self {
+        EventKind::Error {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Ждёт в потоке событий пакет `T`, прошедший `predicate`.
+/// Отключение во время ожидания — `ConnectionAborted`, закрытый канал — `BrokenPipe`.
+pub(crate) async fn wait_on_events<T, F>(
+    events: &mut broadcast::Receiver<ConnectionEvent>,
+    mut predicate: F,
+    timeout: Duration,
+) -> io::Result<T>
+where
+    T: Clone + 'static,
+    F: FnMut(&T) -> bool,
+{
+    let wait = async {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Connection is closed"));
+                }
+            };
+            if let Some(found) = event.packet::<T>().filter(|p| predicate(p)) {
+                return Ok(found.clone());
+            }
+            if let EventKind::Disconnected { reason } = &event.kind {
+                return Err(disconnected_error(reason.as_deref().unwrap_or("End of stream")));
+            }
+        }
+    };
+    tokio::time::timeout(timeout, wait)
+        .await
+        .map_err(|_| wait_timed_out::<T>(timeout))?
+}
@@ -0,0 +1,104 @@
+use crate::auth::offline_uuid;
+use serde::{Deserialize, Serialize};
+use tokio::io;
+use uuid::Uuid;
+
+/// Свойство профиля (скин и т.п.) в том виде, в каком его пересылает BungeeCord
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForwardedProperty {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// IP forwarding BungeeCord: прокси дописывает к адресу в `Handshake` реальный IP
+/// и UUID игрока, а бэкенд с `bungeecord: true` им верит. Позволяет зайти на бэкенд
+/// напрямую так, как если бы игрок пришёл через прокси.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BungeeForwarding {
+    pub client_ip: String,
+    pub uuid: Uuid,
+    pub properties: Vec<ForwardedProperty>,
+}
+
+impl BungeeForwarding {
+    pub fn new(client_ip: &str, uuid: Uuid) -> Self {
+        Self {
+            client_ip: client_ip.to_string(),
+            uuid,
+            properties: Vec::new(),
+        }
+    }
+
+    /// UUID, который прокси в offline-режиме выдал бы игроку с таким ником
+    pub fn offline(username: &str, client_ip: &str) -> Self {
+        Self::new(client_ip, offline_uuid(username))
+    }
+
+    pub fn with_property(mut self, property: ForwardedProperty) -> Self {
+        self.properties.push(property);
+        self
+    }
+
+    /// `host\0ip\0uuid[\0properties]`: UUID без дефисов, свойства — JSON-массив
+    pub fn server_address(&self, host: &str) -> String {
+        let mut address = format!("{}\0{}\0{}", host, self.client_ip, self.uuid.simple());
+        if !self.properties.is_empty() {
+            address.push('\0');
+            // Сериализация Vec из строк не падает
+            address.push_str(&serde_json::to_string(&self.properties).unwrap());
+        }
+        address
+    }
+
+    /// Разбирает адрес из `Handshake` на виртуальный хост и данные forwarding.
+    /// `None` — адрес без forwarding, в том числе с одним маркером Forge `host\0FML\0`.
+    pub fn parse(address: &str) -> io::Result<Option<(String, Self)>> {
+        let mut parts = address.splitn(4, '\0');
+        let host = parts.next().unwrap_or_default();
+        let (Some(client_ip), Some(uuid)) = (parts.next(), parts.next()) else {
+            return Ok(None);
+        };
+        // Spigot делит адрес через `split("\0")`, который отбрасывает пустые хвосты
+        if client_ip == "FML" || uuid.is_empty() {
+            return Ok(None);
+        }
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let uuid = Uuid::parse_str(uuid).map_err(|e| invalid(e.to_string()))?;
+        let properties = match parts.next() {
+            Some(json) => serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?,
+            None => Vec::new(),
+        };
+        let forwarding = Self {
+            client_ip: client_ip.to_string(),
+            uuid,
+            properties,
+        };
+        Ok(Some((host.to_string(), forwarding)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_round_trip() {
+        let forwarding = BungeeForwarding::offline("Notch", "203.0.113.7").with_property(ForwardedProperty {
+            name: "textures".to_string(),
+            value: "e30=".to_string(),
+            signature: None,
+        });
+        let address = forwarding.server_address("play.example.com");
+        assert_eq!(
+            address,
+            "play.example.com\u{0}203.0.113.7\u{0}b50ad385829d3141a2167e7d7539ba7f\u{0}[{\"name\":\"textures\",\"value\":\"e30=\"}]"
+        );
+        let (host, parsed) = BungeeForwarding::parse(&address).unwrap().unwrap();
+        assert_eq!(host, "play.example.com");
+        assert_eq!(parsed, forwarding);
+        assert_eq!(BungeeForwarding::parse("play.example.com").unwrap(), None);
+        assert_eq!(BungeeForwarding::parse("play.example.com\0FML\0").unwrap(), None);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::connection::connection_state::ConnectionState;
+use crate::connection::forwarding::BungeeForwarding;
+use crate::protocol::fields::{UShort, VarInt, VarString};
+use crate::protocol::packets::Handshake;
+
+/// Версия протокола 1.7.10
+pub const PROTOCOL_VERSION: i32 = 5;
+pub const DEFAULT_PORT: u16 = 25565;
+/// Метка, которую клиент Forge дописывает к адресу в `Handshake`
+pub const FML_MARKER: &str = "\0FML\0";
+
+/// `next_state` в `Handshake`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NextState {
+    Status,
+    Login,
+}
+
+impl NextState {
+    pub fn id(self) -> i32 {
+        match self {
+            NextState::Status => 1,
+            NextState::Login => 2,
+        }
+    }
+
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            1 => Some(NextState::Status),
+            2 => Some(NextState::Login),
+            _ => None,
+        }
+    }
+
+    /// Состояние соединения после отправки `Handshake`
+    pub fn connection_state(self) -> ConnectionState {
+        match self {
+            NextState::Status => ConnectionState::Status,
+            NextState::Login => ConnectionState::Login,
+        }
+    }
+}
+
+/// Что написать в `Handshake`. Хост и порт по умолчанию — те, к которым подключались;
+/// виртуальный хост нужен, когда прокси маршрутизирует по имени, а подключаемся по IP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeOptions {
+    pub protocol_version: i32,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    /// Дописать [`FML_MARKER`]. С IP forwarding не дописывается: BungeeCord так же
+    /// отбрасывает его при пересылке на бэкенд.
+    pub fml: bool,
+    pub forwarding: Option<BungeeForwarding>,
+    pub next_state: NextState,
+}
+
+impl HandshakeOptions {
+    pub fn new(next_state: NextState) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            host: None,
+            port: None,
+            fml: false,
+            forwarding: None,
+            next_state,
+        }
+    }
+
+    pub fn login() -> Self {
+        Self::new(NextState::Login)
+    }
+
+    pub fn status() -> Self {
+        Self::new(NextState::Status)
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn fml(mut self, fml: bool) -> Self {
+        self.fml = fml;
+        self
+    }
+
+    pub fn forwarding(mut self, forwarding: BungeeForwarding) -> Self {
+        self.forwarding = Some(forwarding);
+        self
+    }
+
+    pub fn protocol_version(mut self, protocol_version: i32) -> Self {
+        self.protocol_version = protocol_version;
+        self
+    }
+
+    /// Строка `server_address` с меткой FML или данными forwarding
+    pub fn server_address(&self, default_host: &str) -> String {
+        let host = self.host.as_deref().unwrap_or(default_host);
+        match &self.forwarding {
+            Some(forwarding) => forwarding.server_address(host),
+            None if self.fml => format!("{}{}", host, FML_MARKER),
+            None => host.to_string(),
+        }
+    }
+
+    pub fn build(&self, default_host: &str, default_port: u16) -> Handshake {
+        Handshake {
+            protocol_version: VarInt(self.protocol_version),
+            server_address: VarString(self.server_address(default_host)),
+            server_port: UShort(self.port.unwrap_or(default_port)),
+            next_state: VarInt(self.next_state.id()),
+        }
+    }
+}
+
+/// Хост и порт из адреса вида `host:port`. IPv6 с портом пишется в скобках (`[::1]:25565`),
+/// голый IPv6 (`::1`) целиком считается хостом.
+pub fn split_address(addr: &str) -> (String, u16) {
+    if let Some((host, rest)) = addr.strip_prefix('[').and_then(|a| a.split_once(']')) {
+        let port = rest.strip_prefix(':').and_then(|p| p.parse().ok());
+        return (host.to_string(), port.unwrap_or(DEFAULT_PORT));
+    }
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => (host.to_string(), port.parse().unwrap_or(DEFAULT_PORT)),
+        _ => (addr.to_string(), DEFAULT_PORT),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_virtual_host_with_fml_marker() {
+        let handshake = HandshakeOptions::login()
+            .host("mc.example.com")
+            .port(25577)
+            .fml(true)
+            .build("10.0.0.5", 25565);
+        assert_eq!(handshake.server_address.0, "mc.example.com\0FML\0");
+        assert_eq!(handshake.server_port.0, 25577);
+        assert_eq!(handshake.next_state.0, 2);
+        assert_eq!(handshake.protocol_version.0, PROTOCOL_VERSION);
+
+        let status = HandshakeOptions::status().build("10.0.0.5", 25565);
+        assert_eq!(status.server_address.0, "10.0.0.5");
+        assert_eq!(NextState::from_id(status.next_state.0), Some(NextState::Status));
+    }
+
+    #[test]
+    fn splits_ipv4_names_and_ipv6() {
+        assert_eq!(split_address("mc.example.com:25577"), ("mc.example.com".to_string(), 25577));
+        assert_eq!(split_address("10.0.0.5"), ("10.0.0.5".to_string(), DEFAULT_PORT));
+        assert_eq!(split_address("[::1]:25566"), ("::1".to_string(), 25566));
+        assert_eq!(split_address("[::1]"), ("::1".to_string(), DEFAULT_PORT));
+        assert_eq!(split_address("::1"), ("::1".to_string(), DEFAULT_PORT));
+        assert_eq!(split_address("2001:db8::5"), ("2001:db8::5".to_string(), DEFAULT_PORT));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::connection::connection_state::ConnectionState;
+use crate::connection::handshake::NextState;
+use crate::protocol::crypto::{EncryptedStream, ServerKeyPair};
+use crate::protocol::packets::client::{EncryptionResponse, Handshake};
+use crate::protocol::packets::decoder::{decode_client_packet, read_frame};
+use crate::protocol::packets::{AsyncPacket, AsyncPacketExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{self, AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+
+#[allow(clippy::large_enum_variant)]
+enum IncomingStream<S> {
+    Plain(S),
+    Encrypted(EncryptedStream<S>),
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for IncomingStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IncomingStream::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            IncomingStream::Encrypted(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for IncomingStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            IncomingStream::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            IncomingStream::Encrypted(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IncomingStream::Plain(s) => Pin::new(s).poll_flush(cx),
+            IncomingStream::Encrypted(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IncomingStream::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            IncomingStream::Encrypted(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Серверная сторона соединения: клиент, подключившийся к mock-серверу.
+/// Читает клиентские пакеты, пишет серверные и умеет включать шифрование,
+/// как vanilla-сервер в online-режиме.
+pub struct IncomingConnection<S = TcpStream> {
+    /// `None` только на время переключения на шифрование
+    stream: Option<IncomingStream<S>>,
+    state: ConnectionState,
+}
+
+impl IncomingConnection<TcpStream> {
+    pub async fn accept(listener: &TcpListener) -> io::Result<Self> {
+        let (stream, _) = listener.accept().await?;
+        Ok(Self::new(stream))
+    }
+}
+
+impl<S> IncomingConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: Some(IncomingStream::Plain(stream)),
+            state: ConnectionState::Handshaking,
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: ConnectionState) {
+        self.state = state;
+    }
+
+    fn stream(&mut self) -> io::Result<&mut IncomingStream<S>> {
+        self.stream
+            .as_mut()
+            .ok_or_else(|| io::Error::other("No stream available"))
+    }
+
+    /// Читает следующий пакет клиента. `Handshake` сразу переводит соединение
+    /// в состояние из `next_state`.
+    pub async fn read_packet(&mut self) -> io::Result<Box<dyn AsyncPacket + Send>> {
+        let frame = read_frame(self.stream()?).await?;
+        let packet = decode_client_packet(&frame, self.state).await?;
+        if let Some(handshake) = packet.as_packet::<Handshake>() {
+            let next_state = NextState::from_id(handshake.next_state.0).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown next state in handshake: {}", handshake.next_state.0),
+                )
+            })?;
+            self.state = next_state.connection_state();
+        }
+        Ok(packet)
+    }
+
+    /// Читает следующий пакет и требует, чтобы он был типа `T`
+    pub async fn expect<T>(&mut self) -> io::Result<T>
+    where
+        T: Clone + 'static,
+    {
+        let packet = self.read_packet().await?;
+        packet.as_packet::<T>().cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Expected {}, got packet 0x{:X}",
+                    std::any::type_name::<T>(),
+                    packet.get_id()
+                ),
+            )
+        })
+    }
+
+    pub async fn send_packet<P>(&mut self, packet: &P) -> io::Result<()>
+    where
+        P: AsyncPacket,
+    {
+        let mut buf = Vec::new();
+        packet.write_to_boxed(&mut buf).await?;
+        let stream = self.stream()?;
+        stream.write_all(&buf).await?;
+        stream.flush().await
+    }
+
+    /// Дальше весь трафик в обе стороны идёт через AES/CFB8 с ключом `secret`
+    pub fn enable_encryption(&mut self, secret: &[u8; 16]) -> io::Result<()> {
+        let stream = match self.stream.take() {
+            Some(IncomingStream::Plain(stream)) => stream,
+            Some(encrypted) => {
+                self.stream = Some(encrypted);
+                return Err(io::Error::other("Encryption is already enabled"));
+            }
+            None => return Err(io::Error::other("No stream available")),
+        };
+        self.stream = Some(IncomingStream::Encrypted(EncryptedStream::new(stream, secret)?));
+        Ok(())
+    }
+
+    /// Шифрование со стороны сервера: `EncryptionRequest`, ожидание `EncryptionResponse`,
+    /// проверка verify token и переключение потока. Возвращает общий секрет,
+    /// по которому можно проверить join (см. `SessionServer::verify`).
+    pub async fn negotiate_encryption(&mut self, keys: &ServerKeyPair, server_id: &str) -> io::Result<[u8; 16]> {
+        let request = keys.encryption_request(server_id);
+        self.send_packet(&request).await?;
+        let response: EncryptionResponse = self.expect().await?;
+        let secret = keys.accept_response(&request, &response)?;
+        self.enable_encryption(&secret)?;
+        Ok(secret)
+    }
+}
@@ -0,0 +1,259 @@
+use crate::auth::{offline_uuid, AuthBackend, LauncherBackend, OfflineBackend};
+use crate::connection::connection::{wait_timed_out, Connection};
+use crate::connection::connection_state::ConnectionState;
+use crate::connection::events::EventKind;
+use crate::connection::handshake::{HandshakeOptions, NextState};
+use crate::protocol::channels::minecraft::DEFAULT_BRAND;
+use crate::protocol::chat::{self, Language};
+use crate::protocol::fields::{ByteArrayShort, VarString};
+use crate::protocol::fml::{FmlHandshake, ModProfile};
+use crate::protocol::packets::server::{LoginSuccess, ServerPacket};
+use crate::protocol::packets::LoginStart;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io;
+use uuid::Uuid;
+
+pub use crate::connection::handshake::PROTOCOL_VERSION;
+
+pub use crate::auth::SessionCredentials;
+
+#[derive(Debug, Clone)]
+pub struct LoginOptions {
+    pub username: String,
+    /// Дополнительное поле `LoginStart` (HWID лаунчера)
+    pub devices: Vec<u8>,
+    pub session: SessionCredentials,
+    /// Куда отправлять join, если сервер попросит шифрование
+    pub auth: Arc<dyn AuthBackend>,
+    /// UUID, который должен прийти в `LoginSuccess`. Если пришёл другой — `UuidMismatch`.
+    pub expected_uuid: Option<Uuid>,
+    /// Виртуальный хост, порт, метка FML и IP forwarding для `Handshake`
+    pub handshake: HandshakeOptions,
+    /// Сколько ждать `LoginSuccess`/`LoginDisconnect`, включая шифрование и join
+    pub timeout: Duration,
+    /// Язык, на котором рендерится причина отказа
+    pub language: Language,
+    /// Моды для рукопожатия FML. `None` — голый Forge (`FmlHandshake::default`).
+    pub mod_profile: Option<ModProfile>,
+    /// `MC|Brand` после входа в мир. `None` — не отправлять.
+    pub brand: Option<String>,
+}
+
+impl LoginOptions {
+    pub fn new(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            devices: Vec::new(),
+            session: SessionCredentials::default(),
+            auth: Arc::new(LauncherBackend::mcskill()),
+            expected_uuid: None,
+            handshake: HandshakeOptions::login(),
+            timeout: Duration::from_secs(30),
+            language: Language::default(),
+            mod_profile: None,
+            brand: Some(DEFAULT_BRAND.to_string()),
+        }
+    }
+
+    /// Вход на offline-сервер: join не выполняется, а UUID из `LoginSuccess`
+    /// сверяется с тем, который выдал бы vanilla-сервер в offline-режиме
+    pub fn offline(username: &str) -> Self {
+        Self {
+            auth: Arc::new(OfflineBackend),
+            expected_uuid: Some(offline_uuid(username)),
+            ..Self::new(username)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginOutcome {
+    Success { uuid: String, username: String },
+    /// Вход прошёл, но сервер выдал не тот UUID, что ожидался (`LoginOptions::expected_uuid`).
+    /// Для offline-идентичности это значит, что сервер на самом деле в online-режиме
+    /// или UUID подменяет прокси. Соединение остаётся в состоянии Play.
+    UuidMismatch { expected: Uuid, uuid: String, username: String },
+    /// Сервер отказал во входе; `reason` уже отрендерен из JSON
+    Disconnected { reason: String },
+}
+
+impl Connection {
+    /// Проводит вход целиком: `Handshake`, `LoginStart`, необязательное шифрование
+    /// с join на сессионном сервере, и ждёт `LoginSuccess` или `LoginDisconnect`.
+    /// Соединение читается здесь же, поэтому вызывать до `run`.
+    pub async fn login(&mut self, options: LoginOptions) -> io::Result<LoginOutcome> {
+        if options.handshake.next_state != NextState::Login {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Login requires a handshake with NextState::Login",
+            ));
+        }
+        let (default_host, default_port) = self.target();
+        let handshake = options.handshake.build(&default_host, default_port);
+        let login_start = LoginStart {
+            name: VarString(options.username.clone()),
+            devices: ByteArrayShort(options.devices.clone()),
+        };
+
+        self.session = options.session.clone();
+        self.auth = options.auth.clone();
+        self.brand = options.brand.clone();
+        self.fml = match &options.mod_profile {
+            Some(profile) => FmlHandshake::with_profile(profile),
+            None => FmlHandshake::default(),
+        };
+        let handle = self.handle();
+        handle.send_packet(&handshake).await?;
+        self.set_state(ConnectionState::Login);
+        handle.send_packet(&login_start).await?;
+
+        self.announce();
+        let wait = async {
+            loop {
+                let (_, packet) = self.read_packet().await?;
+                match packet {
+                    Some(ServerPacket::LoginSuccess(LoginSuccess { uuid, username })) => {
+                        return Ok(match options.expected_uuid {
+                            Some(expected) if Uuid::parse_str(&uuid.0).ok() != Some(expected) => {
+                                LoginOutcome::UuidMismatch {
+                                    expected,
+                                    uuid: uuid.0,
+                                    username: username.0,
+                                }
+                            }
+                            _ => LoginOutcome::Success {
+                                uuid: uuid.0,
+                                username: username.0,
+                            },
+                        });
+                    }
+                    Some(ServerPacket::LoginDisconnect(packet)) => {
+                        handle.publish(EventKind::Disconnected {
+                            reason: Some(packet.reason.0.clone()),
+                        });
+                        return Ok(LoginOutcome::Disconnected {
+                            reason: chat::render_json(&packet.reason.0, options.language),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        };
+        tokio::time::timeout(options.timeout, wait)
+            .await
+            .map_err(|_| wait_timed_out::<LoginSuccess>(options.timeout))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::packets::Handshake;
+    use crate::protocol::io::{write_varint, write_varstring};
+    use crate::protocol::packets::decoder::read_frame;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    /// Offline-сервер без шифрования: сразу отвечает `LoginSuccess` с заданным UUID
+    async fn offline_server(uuid: String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_frame(&mut socket).await.unwrap(); // Handshake
+            read_frame(&mut socket).await.unwrap(); // LoginStart
+
+            let mut success = Vec::new();
+            write_varint(&mut success, 0x02).await.unwrap();
+            write_varstring(&mut success, &uuid).await.unwrap();
+            write_varstring(&mut success, "bot").await.unwrap();
+            let mut frame = Vec::new();
+            write_varint(&mut frame, success.len() as i32).await.unwrap();
+            frame.extend_from_slice(&success);
+            socket.write_all(&frame).await.unwrap();
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn offline_login_accepts_offline_uuid() {
+        let addr = offline_server(offline_uuid("bot").to_string()).await;
+        let mut conn = Connection::connect(&addr).await.unwrap();
+        let outcome = conn.login(LoginOptions::offline("bot")).await.unwrap();
+        assert!(matches!(outcome, LoginOutcome::Success { .. }), "{:?}", outcome);
+    }
+
+    #[tokio::test]
+    async fn offline_login_reports_uuid_mismatch() {
+        let online_uuid = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+        let addr = offline_server(online_uuid.to_string()).await;
+        let mut conn = Connection::connect(&addr).await.unwrap();
+        let outcome = conn.login(LoginOptions::offline("bot")).await.unwrap();
+        assert_eq!(
+            outcome,
+            LoginOutcome::UuidMismatch {
+                expected: offline_uuid("bot"),
+                uuid: online_uuid.to_string(),
+                username: "bot".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn encrypted_login_over_duplex() {
+        use crate::connection::incoming::IncomingConnection;
+        use crate::protocol::crypto::ServerKeyPair;
+
+        let (client, server) = tokio::io::duplex(256);
+        let server = tokio::spawn(async move {
+            let keys = ServerKeyPair::generate()?;
+            let mut client = IncomingConnection::new(server);
+            client.expect::<Handshake>().await?;
+            client.expect::<LoginStart>().await?;
+            client.negotiate_encryption(&keys, "").await?;
+            let success = LoginSuccess {
+                uuid: VarString(offline_uuid("bot").to_string()),
+                username: VarString("bot".to_string()),
+            };
+            client.send_packet(&success).await
+        });
+
+        let mut conn = Connection::from_stream(client, "localhost:25565");
+        let outcome = conn.login(LoginOptions::offline("bot")).await.unwrap();
+        assert!(matches!(outcome, LoginOutcome::Success { .. }), "{:?}", outcome);
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn bungee_forwarding_goes_into_handshake() {
+        use crate::connection::forwarding::BungeeForwarding;
+        use crate::connection::incoming::IncomingConnection;
+
+        let (client, server) = tokio::io::duplex(256);
+        let server = tokio::spawn(async move {
+            let mut client = IncomingConnection::new(server);
+            let handshake: Handshake = client.expect().await?;
+            client.expect::<LoginStart>().await?;
+            let (_, forwarding) = BungeeForwarding::parse(&handshake.server_address.0)?.unwrap();
+            let success = LoginSuccess {
+                uuid: VarString(forwarding.uuid.to_string()),
+                username: VarString("bot".to_string()),
+            };
+            client.send_packet(&success).await?;
+            Ok::<_, io::Error>((handshake.server_address.0, forwarding))
+        });
+
+        let mut conn = Connection::from_stream(client, "backend.local:25566");
+        let options = LoginOptions {
+            handshake: HandshakeOptions::login().forwarding(BungeeForwarding::offline("bot", "198.51.100.4")),
+            ..LoginOptions::offline("bot")
+        };
+        let outcome = conn.login(options).await.unwrap();
+        assert!(matches!(outcome, LoginOutcome::Success { .. }), "{:?}", outcome);
+
+        let (address, forwarding) = server.await.unwrap().unwrap();
+        assert!(address.starts_with("backend.local\u{0}198.51.100.4\u{0}"));
+        assert_eq!(forwarding.uuid, offline_uuid("bot"));
+    }
+}
@@ -1,4 +1,14 @@
+#[allow(clippy::module_inception)]
 pub mod connection;
+pub mod channels;
 pub mod connection_state;
 pub mod conn_reader;
+pub mod conn_writer;
+pub mod connection_handle;
+pub mod default_handler;
+pub mod events;
+pub mod forwarding;
+pub mod handshake;
+pub mod incoming;
+pub mod login;
 mod connection_packet_handler;
@@ -0,0 +1,300 @@
+//! Канал `BungeeCord`: плагины бэкенда шлют через игрока запросы прокси, прокси отвечает
+//! в тот же канал. Все строки — `writeUTF`, первым идёт имя подканала.
+//!
+//! Настоящий BungeeCord не пропускает этот канал от клиента к серверу, поэтому при
+//! подключении к бэкенду напрямую бот сам играет роль прокси: читает [`BungeeRequest`]
+//! и отвечает [`BungeeResponse`].
+
+use crate::connection::channels::PluginMessage;
+use crate::protocol::io::{read_i32_be, read_java_utf, read_u16_be, write_i32_be, write_java_utf, write_u16_be};
+use async_trait::async_trait;
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+
+pub const BUNGEE_CHANNEL: &str = "BungeeCord";
+/// Имя сервера в `PlayerCount`/`PlayerList`/`Forward`, означающее «все сервера»
+pub const ALL_SERVERS: &str = "ALL";
+/// Подканалы самого BungeeCord, ответ на которые здесь не разобран.
+/// Принять их за пересланное сообщение значит молча разобрать мусор.
+const UNMODELLED_SUBCHANNELS: [&str; 9] = [
+    "GetPlayerServer",
+    "Connect",
+    "ConnectOther",
+    "Message",
+    "MessageRaw",
+    "Forward",
+    "ForwardToPlayer",
+    "KickPlayer",
+    "KickPlayerRaw",
+];
+
+/// Сообщения от бэкенда к прокси
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BungeeRequest {
+    Connect { server: String },
+    ConnectOther { player: String, server: String },
+    Ip,
+    IpOther { player: String },
+    PlayerCount { server: String },
+    PlayerList { server: String },
+    GetServers,
+    GetServer,
+    Message { player: String, message: String },
+    /// Сервер назначения, `ALL` или `ONLINE`
+    Forward { server: String, channel: String, data: Vec<u8> },
+    ForwardToPlayer { player: String, channel: String, data: Vec<u8> },
+    Uuid,
+    UuidOther { player: String },
+    ServerIp { server: String },
+    KickPlayer { player: String, reason: String },
+}
+
+/// Ответы прокси и пересланные через `Forward` сообщения
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BungeeResponse {
+    Ip { ip: String, port: i32 },
+    IpOther { player: String, ip: String, port: i32 },
+    PlayerCount { server: String, count: i32 },
+    PlayerList { server: String, players: Vec<String> },
+    GetServers { servers: Vec<String> },
+    GetServer { server: String },
+    Uuid { uuid: String },
+    UuidOther { player: String, uuid: String },
+    ServerIp { server: String, ip: String, port: u16 },
+    /// Сообщение, которое другой сервер отправил через `Forward`: подканал — его канал.
+    /// Канал не может совпадать с подканалом BungeeCord.
+    Forwarded { channel: String, data: Vec<u8> },
+}
+
+#[async_trait]
+impl PluginMessage for BungeeRequest {
+    async fn decode(mut data: &[u8]) -> io::Result<Self> {
+        let r = &mut data;
+        let subchannel = read_java_utf(r).await?;
+        let request = match subchannel.as_str() {
+            "Connect" => BungeeRequest::Connect { server: read_java_utf(r).await? },
+            "ConnectOther" => BungeeRequest::ConnectOther {
+                player: read_java_utf(r).await?,
+                server: read_java_utf(r).await?,
+            },
+            "IP" => BungeeRequest::Ip,
+            "IPOther" => BungeeRequest::IpOther { player: read_java_utf(r).await? },
+            "PlayerCount" => BungeeRequest::PlayerCount { server: read_java_utf(r).await? },
+            "PlayerList" => BungeeRequest::PlayerList { server: read_java_utf(r).await? },
+            "GetServers" => BungeeRequest::GetServers,
+            "GetServer" => BungeeRequest::GetServer,
+            "Message" => BungeeRequest::Message {
+                player: read_java_utf(r).await?,
+                message: read_java_utf(r).await?,
+            },
+            "Forward" => BungeeRequest::Forward {
+                server: read_java_utf(r).await?,
+                channel: read_java_utf(r).await?,
+                data: read_short_bytes(r).await?,
+            },
+            "ForwardToPlayer" => BungeeRequest::ForwardToPlayer {
+                player: read_java_utf(r).await?,
+                channel: read_java_utf(r).await?,
+                data: read_short_bytes(r).await?,
+            },
+            "UUID" => BungeeRequest::Uuid,
+            "UUIDOther" => BungeeRequest::UuidOther { player: read_java_utf(r).await? },
+            "ServerIP" => BungeeRequest::ServerIp { server: read_java_utf(r).await? },
+            "KickPlayer" => BungeeRequest::KickPlayer {
+                player: read_java_utf(r).await?,
+                reason: read_java_utf(r).await?,
+            },
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown BungeeCord subchannel: {}", other),
+                ))
+            }
+        };
+        Ok(request)
+    }
+
+    async fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let w = &mut buf;
+        match self {
+            BungeeRequest::Connect { server } => write_utfs(w, &["Connect", server]).await?,
+            BungeeRequest::ConnectOther { player, server } => write_utfs(w, &["ConnectOther", player, server]).await?,
+            BungeeRequest::Ip => write_utfs(w, &["IP"]).await?,
+            BungeeRequest::IpOther { player } => write_utfs(w, &["IPOther", player]).await?,
+            BungeeRequest::PlayerCount { server } => write_utfs(w, &["PlayerCount", server]).await?,
+            BungeeRequest::PlayerList { server } => write_utfs(w, &["PlayerList", server]).await?,
+            BungeeRequest::GetServers => write_utfs(w, &["GetServers"]).await?,
+            BungeeRequest::GetServer => write_utfs(w, &["GetServer"]).await?,
+            BungeeRequest::Message { player, message } => write_utfs(w, &["Message", player, message]).await?,
+            BungeeRequest::Forward { server, channel, data } => {
+                write_utfs(w, &["Forward", server, channel]).await?;
+                write_short_bytes(w, data).await?;
+            }
+            BungeeRequest::ForwardToPlayer { player, channel, data } => {
+                write_utfs(w, &["ForwardToPlayer", player, channel]).await?;
+                write_short_bytes(w, data).await?;
+            }
+            BungeeRequest::Uuid => write_utfs(w, &["UUID"]).await?,
+            BungeeRequest::UuidOther { player } => write_utfs(w, &["UUIDOther", player]).await?,
+            BungeeRequest::ServerIp { server } => write_utfs(w, &["ServerIP", server]).await?,
+            BungeeRequest::KickPlayer { player, reason } => write_utfs(w, &["KickPlayer", player, reason]).await?,
+        }
+        Ok(buf)
+    }
+}
+
+#[async_trait]
+impl PluginMessage for BungeeResponse {
+    async fn decode(mut data: &[u8]) -> io::Result<Self> {
+        let r = &mut data;
+        let subchannel = read_java_utf(r).await?;
+        let response = match subchannel.as_str() {
+            "IP" => BungeeResponse::Ip {
+                ip: read_java_utf(r).await?,
+                port: read_i32_be(r).await?,
+            },
+            "IPOther" => BungeeResponse::IpOther {
+                player: read_java_utf(r).await?,
+                ip: read_java_utf(r).await?,
+                port: read_i32_be(r).await?,
+            },
+            "PlayerCount" => BungeeResponse::PlayerCount {
+                server: read_java_utf(r).await?,
+                count: read_i32_be(r).await?,
+            },
+            "PlayerList" => BungeeResponse::PlayerList {
+                server: read_java_utf(r).await?,
+                players: split_list(&read_java_utf(r).await?),
+            },
+            "GetServers" => BungeeResponse::GetServers {
+                servers: split_list(&read_java_utf(r).await?),
+            },
+            "GetServer" => BungeeResponse::GetServer { server: read_java_utf(r).await? },
+            "UUID" => BungeeResponse::Uuid { uuid: read_java_utf(r).await? },
+            "UUIDOther" => BungeeResponse::UuidOther {
+                player: read_java_utf(r).await?,
+                uuid: read_java_utf(r).await?,
+            },
+            "ServerIP" => BungeeResponse::ServerIp {
+                server: read_java_utf(r).await?,
+                ip: read_java_utf(r).await?,
+                port: read_u16_be(r).await?,
+            },
+            other if UNMODELLED_SUBCHANNELS.contains(&other) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unsupported BungeeCord response: {}", other),
+                ))
+            }
+            _ => BungeeResponse::Forwarded {
+                channel: subchannel,
+                data: read_short_bytes(r).await?,
+            },
+        };
+        Ok(response)
+    }
+
+    async fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let w = &mut buf;
+        match self {
+            BungeeResponse::Ip { ip, port } => {
+                write_utfs(w, &["IP", ip]).await?;
+                write_i32_be(w, *port).await?;
+            }
+            BungeeResponse::IpOther { player, ip, port } => {
+                write_utfs(w, &["IPOther", player, ip]).await?;
+                write_i32_be(w, *port).await?;
+            }
+            BungeeResponse::PlayerCount { server, count } => {
+                write_utfs(w, &["PlayerCount", server]).await?;
+                write_i32_be(w, *count).await?;
+            }
+            BungeeResponse::PlayerList { server, players } => {
+                write_utfs(w, &["PlayerList", server, &players.join(", ")]).await?
+            }
+            BungeeResponse::GetServers { servers } => write_utfs(w, &["GetServers", &servers.join(", ")]).await?,
+            BungeeResponse::GetServer { server } => write_utfs(w, &["GetServer", server]).await?,
+            BungeeResponse::Uuid { uuid } => write_utfs(w, &["UUID", uuid]).await?,
+            BungeeResponse::UuidOther { player, uuid } => write_utfs(w, &["UUIDOther", player, uuid]).await?,
+            BungeeResponse::ServerIp { server, ip, port } => {
+                write_utfs(w, &["ServerIP", server, ip]).await?;
+                write_u16_be(w, *port).await?;
+            }
+            BungeeResponse::Forwarded { channel, data } => {
+                write_utfs(w, &[channel]).await?;
+                write_short_bytes(w, data).await?;
+            }
+        }
+        Ok(buf)
+    }
+}
+
+async fn write_utfs(w: &mut Vec<u8>, strings: &[&str]) -> io::Result<()> {
+    for s in strings {
+        write_java_utf(w, s).await?;
+    }
+    Ok(())
+}
+
+/// Данные `Forward`: u16-длина, как `writeShort` в примерах BungeeCord
+async fn read_short_bytes(r: &mut &[u8]) -> io::Result<Vec<u8>> {
+    let len = read_u16_be(r).await?;
+    let mut data = vec![0u8; len as usize];
+    r.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+async fn write_short_bytes(w: &mut Vec<u8>, data: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Forwarded data is too long"))?;
+    write_u16_be(w, len).await?;
+    w.write_all(data).await
+}
+
+/// Списки в ответах BungeeCord — через `", "`
+fn split_list(list: &str) -> Vec<String> {
+    list.split(", ")
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn forward_request_layout() {
+        let request = BungeeRequest::Forward {
+            server: ALL_SERVERS.to_string(),
+            channel: "Sync".to_string(),
+            data: vec![1, 2, 3],
+        };
+        let data = request.encode().await.unwrap();
+        assert_eq!(data, b"\0\x07Forward\0\x03ALL\0\x04Sync\0\x03\x01\x02\x03");
+        assert_eq!(BungeeRequest::decode(&data).await.unwrap(), request);
+    }
+
+    #[tokio::test]
+    async fn responses_round_trip() {
+        let responses = [
+            BungeeResponse::PlayerCount { server: "lobby".to_string(), count: 42 },
+            BungeeResponse::GetServers { servers: vec!["lobby".to_string(), "survival".to_string()] },
+            BungeeResponse::ServerIp { server: "lobby".to_string(), ip: "10.0.0.2".to_string(), port: 25566 },
+            BungeeResponse::Forwarded { channel: "Sync".to_string(), data: vec![9] },
+        ];
+        for response in responses {
+            let data = response.encode().await.unwrap();
+            assert_eq!(BungeeResponse::decode(&data).await.unwrap(), response);
+        }
+    }
+
+    #[tokio::test]
+    async fn unmodelled_subchannels_are_not_forwarded() {
+        let mut data = Vec::new();
+        write_utfs(&mut data, &["GetPlayerServer", "tester", "lobby"]).await.unwrap();
+        let err = BungeeResponse::decode(&data).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,168 @@
+//! Vanilla-каналы `MC|` в 1.7.10. Строки (`MC|Brand`, `MC|ItemName`) идут голым UTF-8
+//! без префикса длины, остальное — в формате полей пакетов.
+
+use crate::connection::channels::PluginMessage;
+use crate::protocol::fields::{AsyncReadField, AsyncWriteField, ItemStack};
+use crate::protocol::io::{read_bool, read_i32_be, read_u8_be, write_bool, write_i32_be, write_u8_be};
+use async_trait::async_trait;
+use nbt::{Blob, Tag};
+use tokio::io;
+
+pub const BRAND_CHANNEL: &str = "MC|Brand";
+pub const TRADE_LIST_CHANNEL: &str = "MC|TrList";
+pub const TRADE_SELECT_CHANNEL: &str = "MC|TrSel";
+pub const BOOK_EDIT_CHANNEL: &str = "MC|BEdit";
+pub const BOOK_SIGN_CHANNEL: &str = "MC|BSign";
+pub const ITEM_NAME_CHANNEL: &str = "MC|ItemName";
+
+/// Бренд клиента Forge; vanilla-клиент шлёт `vanilla`
+pub const DEFAULT_BRAND: &str = "fml,forge";
+/// `minecraft:writable_book` — книга с пером: её шлёт `MC|BEdit`, её же сервер ждёт в руке
+pub const WRITABLE_BOOK_ID: i16 = 386;
+/// `minecraft:written_book` — подписанная книга, которую шлёт `MC|BSign`
+pub const WRITTEN_BOOK_ID: i16 = 387;
+
+/// Одна сделка жителя
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerchantRecipe {
+    pub buy: ItemStack,
+    pub buy_second: Option<ItemStack>,
+    pub sell: ItemStack,
+    /// Сделка исчерпана и ждёт обновления
+    pub disabled: bool,
+}
+
+/// `MC|TrList`: сделки открытого окна торговли
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeList {
+    pub window_id: i32,
+    pub recipes: Vec<MerchantRecipe>,
+}
+
+#[async_trait]
+impl PluginMessage for TradeList {
+    async fn decode(mut data: &[u8]) -> io::Result<Self> {
+        let r = &mut data;
+        let window_id = read_i32_be(r).await?;
+        let count = read_u8_be(r).await?;
+        let mut recipes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let buy = ItemStack::read_field(r).await?;
+            let sell = ItemStack::read_field(r).await?;
+            let buy_second = if read_bool(r).await? {
+                Some(ItemStack::read_field(r).await?)
+            } else {
+                None
+            };
+            let disabled = read_bool(r).await?;
+            recipes.push(MerchantRecipe {
+                buy,
+                buy_second,
+                sell,
+                disabled,
+            });
+        }
+        Ok(TradeList { window_id, recipes })
+    }
+
+    async fn encode(&self) -> io::Result<Vec<u8>> {
+        let count = u8::try_from(self.recipes.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many trades"))?;
+        let mut buf = Vec::new();
+        write_i32_be(&mut buf, self.window_id).await?;
+        write_u8_be(&mut buf, count).await?;
+        for recipe in &self.recipes {
+            recipe.buy.write_field(&mut buf).await?;
+            recipe.sell.write_field(&mut buf).await?;
+            write_bool(&mut buf, recipe.buy_second.is_some()).await?;
+            if let Some(second) = &recipe.buy_second {
+                second.write_field(&mut buf).await?;
+            }
+            write_bool(&mut buf, recipe.disabled).await?;
+        }
+        Ok(buf)
+    }
+}
+
+/// `MC|TrSel`: номер выбранной сделки в открытом окне торговли
+pub async fn trade_select(index: i32) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    write_i32_be(&mut buf, index).await?;
+    Ok(buf)
+}
+
+/// `MC|BEdit`: сохранить страницы книги с пером, которая в руке
+pub async fn book_edit(pages: &[String]) -> io::Result<Vec<u8>> {
+    let mut tag = Blob::new();
+    tag.insert("pages", pages_tag(pages));
+    book_payload(WRITABLE_BOOK_ID, &tag).await
+}
+
+/// `MC|BSign`: подписать книгу в руке. Автора сервер берёт из профиля игрока,
+/// но vanilla-клиент всё равно его передаёт.
+pub async fn book_sign(title: &str, author: &str, pages: &[String]) -> io::Result<Vec<u8>> {
+    let mut tag = Blob::new();
+    tag.insert("pages", pages_tag(pages));
+    tag.insert("title", Tag::String(title.to_string()));
+    tag.insert("author", Tag::String(author.to_string()));
+    book_payload(WRITTEN_BOOK_ID, &tag).await
+}
+
+fn pages_tag(pages: &[String]) -> Tag {
+    Tag::List(pages.iter().cloned().map(Tag::String).collect())
+}
+
+async fn book_payload(item_id: i16, tag: &Blob) -> io::Result<Vec<u8>> {
+    let book = ItemStack::new(item_id, 1, 0).with_tag(tag)?;
+    let mut buf = Vec::new();
+    book.write_field(&mut buf).await?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn trade_list_round_trip() {
+        let trades = TradeList {
+            window_id: 3,
+            recipes: vec![
+                MerchantRecipe {
+                    buy: ItemStack::new(388, 5, 0),
+                    buy_second: None,
+                    sell: ItemStack::new(264, 1, 0),
+                    disabled: false,
+                },
+                MerchantRecipe {
+                    buy: ItemStack::new(388, 12, 0),
+                    buy_second: Some(ItemStack::new(340, 1, 0)),
+                    sell: ItemStack::new(403, 1, 0),
+                    disabled: true,
+                },
+            ],
+        };
+        let data = trades.encode().await.unwrap();
+        assert_eq!(&data[..5], [0, 0, 0, 3, 2]);
+        assert_eq!(TradeList::decode(&data).await.unwrap(), trades);
+    }
+
+    #[tokio::test]
+    async fn signed_book_carries_pages_and_title() {
+        let pages = vec!["first".to_string(), "second".to_string()];
+        let data = book_sign("Notes", "tester", &pages).await.unwrap();
+        let book = ItemStack::read_field(&mut &data[..]).await.unwrap();
+        assert_eq!(book.item_id, WRITTEN_BOOK_ID);
+
+        let tag = book.tag().unwrap().unwrap();
+        assert_eq!(tag.elements.get("title"), Some(&Tag::String("Notes".to_string())));
+        assert_eq!(tag.elements.get("pages"), Some(&pages_tag(&pages)));
+    }
+
+    #[tokio::test]
+    async fn edited_book_stays_writable() {
+        let data = book_edit(&["draft".to_string()]).await.unwrap();
+        let book = ItemStack::read_field(&mut &data[..]).await.unwrap();
+        assert_eq!(book.item_id, WRITABLE_BOOK_ID);
+    }
+}
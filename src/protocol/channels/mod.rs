@@ -0,0 +1,4 @@
+//! Форматы сообщений в каналах плагинов (`CustomPayload`)
+
+pub mod bungee;
+pub mod minecraft;
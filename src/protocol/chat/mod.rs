@@ -0,0 +1,204 @@
+//! Перевод чат-компонентов `translate` по таблицам языков 1.7.10.
+//!
+//! Вшиты не полные таблицы vanilla, а выборка ключей, которые сервер шлёт в чат:
+//! `chat.type.*`, `death.*`, `commands.*`, `multiplayer.*`, `disconnect.*` и немногие `tile.*`.
+//! Названий предметов, блоков и достижений (`item.*`, `tile.*`, `achievement.*`) в таблицах нет,
+//! поэтому, например, в `chat.type.achievement` аргумент-достижение останется ключом.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const EN_US: &str = include_str!("lang/en_US.lang");
+const RU_RU: &str = include_str!("lang/ru_RU.lang");
+
+/// Языки, таблицы которых вшиты в бинарник.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    EnUs,
+    RuRu,
+}
+
+impl Language {
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::EnUs => "en_US",
+            Language::RuRu => "ru_RU",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.to_ascii_lowercase().as_str() {
+            "en_us" => Some(Language::EnUs),
+            "ru_ru" => Some(Language::RuRu),
+            _ => None,
+        }
+    }
+
+    pub fn table(self) -> &'static HashMap<String, String> {
+        static EN: OnceLock<HashMap<String, String>> = OnceLock::new();
+        static RU: OnceLock<HashMap<String, String>> = OnceLock::new();
+        match self {
+            Language::EnUs => EN.get_or_init(|| parse_lang(EN_US)),
+            Language::RuRu => RU.get_or_init(|| parse_lang(RU_RU)),
+        }
+    }
+}
+
+fn parse_lang(source: &str) -> HashMap<String, String> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Переводит ключ так же, как `ChatComponentTranslation` в 1.7.10:
+/// `%s` берёт следующий аргумент, `%N$s` — аргумент по номеру, `%%` — литерал.
+/// Неизвестный ключ возвращается как есть, en_US используется как запасной язык.
+pub fn translate(lang: Language, key: &str, args: &[String]) -> String {
+    translate_in(lang.table(), Language::EnUs.table(), key, args)
+}
+
+fn translate_in(table: &HashMap<String, String>, fallback: &HashMap<String, String>, key: &str, args: &[String]) -> String {
+    match table.get(key).or_else(|| fallback.get(key)) {
+        Some(format) => apply_format(format, args),
+        None => key.to_string(),
+    }
+}
+
+fn apply_format(format: &str, args: &[String]) -> String {
+    let mut out = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    let mut next_arg = 0;
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        let mut digits = String::new();
+        while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+            digits.push(*d);
+            chars.next();
+        }
+
+        let index = if !digits.is_empty() && chars.peek() == Some(&'$') {
+            chars.next();
+            digits.parse::<usize>().ok().map(|n| n.saturating_sub(1))
+        } else {
+            out.push_str(&digits);
+            None
+        };
+
+        match chars.next() {
+            Some('%') if index.is_none() => out.push('%'),
+            Some(conv) if conv.is_ascii_alphabetic() => {
+                let index = index.unwrap_or_else(|| {
+                    next_arg += 1;
+                    next_arg - 1
+                });
+                out.push_str(args.get(index).map(String::as_str).unwrap_or(""));
+            }
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+/// Рендерит JSON-компонент чата в простой текст, раскрывая `translate`/`with` и `extra`.
+pub fn render(component: &Value, lang: Language) -> String {
+    let mut out = String::new();
+    render_into(component, lang, &mut out);
+    out
+}
+
+/// То же, что [`render`], но для сырой строки из пакета. Если это не JSON, строка возвращается как есть.
+pub fn render_json(json: &str, lang: Language) -> String {
+    match serde_json::from_str::<Value>(json) {
+        Ok(value) => render(&value, lang),
+        Err(_) => json.to_string(),
+    }
+}
+
+fn render_into(component: &Value, lang: Language, out: &mut String) {
+    match component {
+        Value::String(s) => out.push_str(s),
+        Value::Array(parts) => {
+            for part in parts {
+                render_into(part, lang, out);
+            }
+        }
+        Value::Object(obj) => {
+            if let Some(key) = obj.get("translate").and_then(Value::as_str) {
+                let args: Vec<String> = obj
+                    .get("with")
+                    .and_then(Value::as_array)
+                    .map(|with| with.iter().map(|arg| render(arg, lang)).collect())
+                    .unwrap_or_default();
+                out.push_str(&translate(lang, key, &args));
+            } else if let Some(text) = obj.get("text") {
+                render_into(text, lang, out);
+            }
+
+            if let Some(extra) = obj.get("extra").and_then(Value::as_array) {
+                for part in extra {
+                    render_into(part, lang, out);
+                }
+            }
+        }
+        Value::Null => {}
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn positional_and_sequential_arguments() {
+        assert_eq!(apply_format("%2$s then %1$s", &args(&["a", "b"])), "b then a");
+        assert_eq!(apply_format("<%s> %s", &args(&["Steve", "hi"])), "<Steve> hi");
+        // Недостающий аргумент — пустая строка, как в клиенте
+        assert_eq!(apply_format("%s and %s", &args(&["one"])), "one and ");
+    }
+
+    #[test]
+    fn percent_escape() {
+        assert_eq!(apply_format("100%% of %s", &args(&["it"])), "100% of it");
+        assert_eq!(apply_format("50%", &[]), "50%");
+    }
+
+    #[test]
+    fn fallback_to_en_us_and_unknown_keys() {
+        let ru = parse_lang("chat.type.text=<%s> %s");
+        let en = parse_lang("# комментарий\nchat.type.text=<%s> %s\ncommands.generic.syntax=Invalid command syntax");
+        assert_eq!(translate_in(&ru, &en, "commands.generic.syntax", &[]), "Invalid command syntax");
+        assert_eq!(translate_in(&ru, &en, "no.such.key", &args(&["x"])), "no.such.key");
+        assert_eq!(translate(Language::RuRu, "no.such.key", &[]), "no.such.key");
+    }
+
+    #[test]
+    fn nested_with_components() {
+        let json = r#"{"translate":"death.attack.player","with":[
+            {"text":"Alex"},
+            {"translate":"chat.type.text","with":["Steve",{"text":"hi","extra":["!"]}]}
+        ],"extra":[" ",{"text":"(pvp)"}]}"#;
+        assert_eq!(render_json(json, Language::EnUs), "Alex was slain by <Steve> hi! (pvp)");
+        assert_eq!(render_json(json, Language::RuRu), "Alex был убит <Steve> hi! (pvp)");
+        assert_eq!(render_json("plain text", Language::EnUs), "plain text");
+    }
+}
@@ -2,21 +2,33 @@ use aes::Aes128;
 use cfb8::cipher::{AsyncStreamCipher, NewCipher};
 use cfb8::Cfb8;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
-use tokio::net::TcpStream;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{ready, Context, Poll};
 use std::io;
 
 type AesCfb8 = Cfb8<Aes128>;
 
-pub struct EncryptedStream {
-    stream: TcpStream,
+/// Сколько открытого текста `poll_write` принимает за раз, пока предыдущий
+/// шифротекст не ушёл в поток. Ограничивает рост буфера при медленном приёмнике.
+const MAX_PENDING: usize = 64 * 1024;
+
+/// AES/CFB8 поверх любого транспорта: TCP, половин сокета, прокси, `tokio::io::duplex`.
+///
+/// CFB8 — поточный шифр с состоянием, поэтому каждый байт должен шифроваться ровно один раз.
+/// `poll_write` шифрует принятые байты в `pending` и дописывает их оттуда, даже если
+/// внутренний поток принял меньше или вернул `Pending`; повторный вызов не шифрует их заново.
+pub struct EncryptedStream<S> {
+    stream: S,
     encryptor: AesCfb8,
     decryptor: AesCfb8,
+    /// Зашифрованные, но ещё не записанные байты. Ёмкость переиспользуется между записями.
+    pending: Vec<u8>,
+    /// Сколько байт из `pending` уже записано
+    written: usize,
 }
 
-impl EncryptedStream {
-    pub fn new(stream: TcpStream, key: &[u8]) -> io::Result<Self> {
+impl<S> EncryptedStream<S> {
+    pub fn new(stream: S, key: &[u8]) -> io::Result<Self> {
         if key.len() != 16 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -24,19 +36,48 @@ impl EncryptedStream {
             ));
         }
         let encryptor = AesCfb8::new_from_slices(key, key)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| io::Error::other(e.to_string()))?;
         let decryptor = AesCfb8::new_from_slices(key, key)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| io::Error::other(e.to_string()))?;
 
         Ok(Self {
             stream,
             encryptor,
             decryptor,
+            pending: Vec::new(),
+            written: 0,
         })
     }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncWrite + Unpin> EncryptedStream<S> {
+    /// Дописывает `pending` во внутренний поток. `Ready(Ok)` — буфер пуст.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            let n = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.pending[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "Failed to write encrypted data",
+                )));
+            }
+            self.written += n;
+        }
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
 }
 
-impl AsyncRead for EncryptedStream {
+impl<S: AsyncRead + Unpin> AsyncRead for EncryptedStream<S> {
     fn poll_read(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -56,31 +97,126 @@ impl AsyncRead for EncryptedStream {
     }
 }
 
-impl AsyncWrite for EncryptedStream {
+impl<S: AsyncWrite + Unpin> AsyncWrite for EncryptedStream<S> {
     fn poll_write(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        // При записи мы сначала шифруем данные в локальный буфер,
-        // а потом отправляем их в self.stream
-        let mut encrypted = buf.to_vec();
-        self.encryptor.encrypt(&mut encrypted);
+        let this = self.get_mut();
+        // Пока старый шифротекст не ушёл, новые байты не принимаем:
+        // иначе их пришлось бы зашифровать, не зная, примут ли их
+        ready!(this.poll_drain(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let accepted = &buf[..buf.len().min(MAX_PENDING)];
+        let start = this.pending.len();
+        this.pending.extend_from_slice(accepted);
+        this.encryptor.encrypt(&mut this.pending[start..]);
 
-        Pin::new(&mut self.stream).poll_write(cx, &encrypted)
+        // Байты уже зашифрованы и приняты; если поток сейчас занят,
+        // они уйдут при следующей записи или flush
+        if let Poll::Ready(Err(e)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(accepted.len()))
     }
 
     fn poll_flush(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.stream).poll_flush(cx)
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.stream).poll_flush(cx)
     }
 
     fn poll_shutdown(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.stream).poll_shutdown(cx)
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const KEY: [u8; 16] = *b"0123456789abcdef";
+
+    /// Поток, который через раз отвечает `Pending` и принимает не больше `max` байт
+    struct ChokedWriter {
+        out: Vec<u8>,
+        max: usize,
+        choke: bool,
+    }
+
+    impl AsyncWrite for ChokedWriter {
+        fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            self.choke = !self.choke;
+            if self.choke {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = buf.len().min(self.max);
+            self.out.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn partial_writes_do_not_desync_cipher() {
+        let writer = ChokedWriter {
+            out: Vec::new(),
+            max: 7,
+            choke: false,
+        };
+        let mut stream = EncryptedStream::new(writer, &KEY).unwrap();
+        let plain = message(10_000);
+        for chunk in plain.chunks(333) {
+            stream.write_all(chunk).await.unwrap();
+        }
+        stream.flush().await.unwrap();
+
+        let mut decrypted = stream.into_inner().out;
+        assert_eq!(decrypted.len(), plain.len());
+        AesCfb8::new_from_slices(&KEY, &KEY).unwrap().decrypt(&mut decrypted);
+        assert_eq!(decrypted, plain);
+    }
+
+    #[tokio::test]
+    async fn round_trip_over_duplex_under_backpressure() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = EncryptedStream::new(client, &KEY).unwrap();
+        let mut server = EncryptedStream::new(server, &KEY).unwrap();
+        let plain = message(100_000);
+
+        let expected = plain.clone();
+        let reader = tokio::spawn(async move {
+            let mut received = vec![0u8; expected.len()];
+            server.read_exact(&mut received).await.unwrap();
+            assert_eq!(received, expected);
+        });
+        client.write_all(&plain).await.unwrap();
+        client.flush().await.unwrap();
+        reader.await.unwrap();
     }
 }
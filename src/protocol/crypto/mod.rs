@@ -1,6 +1,5 @@
 use rand::rngs::OsRng;
 use rand::RngCore;
-use rsa::pkcs1::DecodeRsaPublicKey;
 use rsa::{PaddingScheme, PublicKey, RsaPublicKey};
 use std::io;
 use rsa::pkcs8::DecodePublicKey;
@@ -13,7 +12,7 @@ pub (crate) fn encrypt_with_server_pubkey(data: &[u8], server_pub_key_der: &[u8]
     let padding = PaddingScheme::new_pkcs1v15_encrypt();
     let mut rng = OsRng;
     public_key.encrypt(&mut rng, padding, data)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        .map_err(io::Error::other)
 }
 
 pub (crate) fn generate_shared_secret() -> [u8; 16] {
@@ -24,4 +23,6 @@ pub (crate) fn generate_shared_secret() -> [u8; 16] {
 }
 
 pub mod encrypted_stream;
+pub mod server_keys;
 pub use encrypted_stream::EncryptedStream;
+pub use server_keys::ServerKeyPair;
@@ -0,0 +1,105 @@
+use crate::protocol::fields::{ByteArrayShort, VarString};
+use crate::protocol::packets::client::EncryptionResponse;
+use crate::protocol::packets::server::EncryptionRequest;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rsa::pkcs8::EncodePublicKey;
+use rsa::{PaddingScheme, RsaPrivateKey, RsaPublicKey};
+use std::io;
+
+/// Размер ключа vanilla-сервера
+const KEY_BITS: usize = 1024;
+const VERIFY_TOKEN_LEN: usize = 4;
+
+/// RSA-ключ сервера для шифрования при входе: то, что держит vanilla-сервер
+/// в online-режиме. Нужен mock-серверам, чтобы принимать настоящих клиентов.
+pub struct ServerKeyPair {
+    private_key: RsaPrivateKey,
+    public_der: Vec<u8>,
+}
+
+impl ServerKeyPair {
+    pub fn generate() -> io::Result<Self> {
+        let private_key = RsaPrivateKey::new(&mut OsRng, KEY_BITS).map_err(io::Error::other)?;
+        let public_der = RsaPublicKey::from(&private_key)
+            .to_public_key_der()
+            .map_err(io::Error::other)?
+            .as_ref()
+            .to_vec();
+        Ok(Self {
+            private_key,
+            public_der,
+        })
+    }
+
+    /// Открытый ключ в X.509 SubjectPublicKeyInfo DER, как он уходит в `EncryptionRequest`
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_der
+    }
+
+    /// `EncryptionRequest` со случайным verify token.
+    /// Сам запрос нужно сохранить: по нему проверяется ответ.
+    pub fn encryption_request(&self, server_id: &str) -> EncryptionRequest {
+        let mut verify_token = vec![0u8; VERIFY_TOKEN_LEN];
+        OsRng.fill_bytes(&mut verify_token);
+        EncryptionRequest {
+            server_id: VarString(server_id.to_string()),
+            public_key: ByteArrayShort(self.public_der.clone()),
+            verify_token: ByteArrayShort(verify_token),
+        }
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.private_key
+            .decrypt(PaddingScheme::new_pkcs1v15_encrypt(), data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Расшифровывает ответ клиента и возвращает общий секрет.
+    /// Ошибка, если verify token не совпал с отправленным или секрет не 16 байт.
+    pub fn accept_response(&self, request: &EncryptionRequest, response: &EncryptionResponse) -> io::Result<[u8; 16]> {
+        let verify_token = self.decrypt(&response.verify_token.0)?;
+        if verify_token != request.verify_token.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Verify token mismatch"));
+        }
+        self.decrypt(&response.shared_secret.0)?
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Shared secret must be 16 bytes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::crypto::{encrypt_with_server_pubkey, generate_shared_secret};
+
+    fn response(keys: &ServerKeyPair, secret: &[u8], token: &[u8]) -> EncryptionResponse {
+        EncryptionResponse {
+            shared_secret: ByteArrayShort(encrypt_with_server_pubkey(secret, keys.public_key_der()).unwrap()),
+            verify_token: ByteArrayShort(encrypt_with_server_pubkey(token, keys.public_key_der()).unwrap()),
+        }
+    }
+
+    #[test]
+    fn accepts_response_from_client_side_crypto() {
+        let keys = ServerKeyPair::generate().unwrap();
+        let request = keys.encryption_request("");
+        assert_eq!(request.verify_token.0.len(), VERIFY_TOKEN_LEN);
+
+        let secret = generate_shared_secret();
+        let accepted = keys.accept_response(&request, &response(&keys, &secret, &request.verify_token.0));
+        assert_eq!(accepted.unwrap(), secret);
+    }
+
+    #[test]
+    fn rejects_wrong_token_and_short_secret() {
+        let keys = ServerKeyPair::generate().unwrap();
+        let request = keys.encryption_request("");
+        let secret = generate_shared_secret();
+
+        let wrong_token = response(&keys, &secret, &[0, 0, 0, 0, 0]);
+        assert!(keys.accept_response(&request, &wrong_token).is_err());
+        let short_secret = response(&keys, &secret[..8], &request.verify_token.0);
+        assert!(keys.accept_response(&request, &short_secret).is_err());
+    }
+}
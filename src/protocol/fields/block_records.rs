@@ -0,0 +1,150 @@
+use crate::protocol::io::{read_i16_be, read_i32_be, read_i8, write_i16_be, write_i32_be, write_i8};
+use async_trait::async_trait;
+use tokio::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Изменённый блок из `MultiBlockChange`. Координаты локальные внутри столбца чанка.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRecord {
+    pub x: u8,
+    pub y: u8,
+    pub z: u8,
+    pub block_id: u16,
+    pub meta: u8,
+}
+
+impl BlockRecord {
+    /// На проводе запись — int: x (4 бита), z (4), y (8), id блока (12), meta (4)
+    pub fn from_packed(packed: u32) -> Self {
+        Self {
+            x: (packed >> 28) as u8,
+            z: (packed >> 24) as u8 & 0x0F,
+            y: (packed >> 16) as u8,
+            block_id: (packed >> 4) as u16 & 0x0FFF,
+            meta: packed as u8 & 0x0F,
+        }
+    }
+
+    pub fn packed(&self) -> u32 {
+        (self.x as u32 & 0x0F) << 28
+            | (self.z as u32 & 0x0F) << 24
+            | (self.y as u32) << 16
+            | (self.block_id as u32 & 0x0FFF) << 4
+            | self.meta as u32 & 0x0F
+    }
+}
+
+/// Записи `MultiBlockChange`: short-число записей, int-размер данных (по 4 байта на запись)
+/// и сами записи
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockRecords(pub Vec<BlockRecord>);
+
+#[async_trait]
+impl crate::protocol::fields::AsyncReadField for BlockRecords {
+    async fn read_field<R>(r: &mut R) -> io::Result<Self>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let count = read_i16_be(r).await? as u16 as usize;
+        let data_size = read_i32_be(r).await?;
+        if data_size < 0 || data_size as usize != count * 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("MultiBlockChange has {} records in {} bytes", count, data_size),
+            ));
+        }
+        let mut records = Vec::with_capacity(count);
+        for _ in 0..count {
+            records.push(BlockRecord::from_packed(read_i32_be(r).await? as u32));
+        }
+        Ok(BlockRecords(records))
+    }
+}
+
+#[async_trait]
+impl crate::protocol::fields::AsyncWriteField for BlockRecords {
+    async fn write_field<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let count = u16::try_from(self.0.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many MultiBlockChange records"))?;
+        write_i16_be(w, count as i16).await?;
+        write_i32_be(w, count as i32 * 4).await?;
+        for record in &self.0 {
+            write_i32_be(w, record.packed() as i32).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Разрушенный взрывом блок: смещение от центра взрыва, округлённого к целому
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExplosionRecord {
+    pub dx: i8,
+    pub dy: i8,
+    pub dz: i8,
+}
+
+/// Записи `Explosion`: int-число записей и по три байта смещений на запись
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExplosionRecords(pub Vec<ExplosionRecord>);
+
+#[async_trait]
+impl crate::protocol::fields::AsyncReadField for ExplosionRecords {
+    async fn read_field<R>(r: &mut R) -> io::Result<Self>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let count = read_i32_be(r).await?;
+        if count < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Negative Explosion record count"));
+        }
+        let mut records = Vec::new();
+        for _ in 0..count {
+            records.push(ExplosionRecord {
+                dx: read_i8(r).await?,
+                dy: read_i8(r).await?,
+                dz: read_i8(r).await?,
+            });
+        }
+        Ok(ExplosionRecords(records))
+    }
+}
+
+#[async_trait]
+impl crate::protocol::fields::AsyncWriteField for ExplosionRecords {
+    async fn write_field<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        write_i32_be(w, self.0.len() as i32).await?;
+        for record in &self.0 {
+            write_i8(w, record.dx).await?;
+            write_i8(w, record.dy).await?;
+            write_i8(w, record.dz).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::fields::{AsyncReadField, AsyncWriteField};
+
+    #[tokio::test]
+    async fn records_use_packed_int_layout() {
+        // x=3, z=10, y=70, id=0x105, meta=9
+        let bytes = [0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x3A, 0x46, 0x10, 0x59];
+        let records = BlockRecords::read_field(&mut &bytes[..]).await.unwrap();
+        assert_eq!(
+            records.0,
+            [BlockRecord { x: 3, y: 70, z: 10, block_id: 0x105, meta: 9 }]
+        );
+
+        let mut buf = Vec::new();
+        records.write_field(&mut buf).await.unwrap();
+        assert_eq!(buf, bytes);
+    }
+}
@@ -28,7 +28,19 @@ packet_field! {
     }
 }
 
+packet_field! {
+    /// Данные `CustomPayload` от Forge-сервера: длина varshort, до 2 МиБ в одном пакете
+    ByteArrayVarShort(Vec<u8>) {
+        async fn read(r: &mut impl AsyncRead + Unpin) -> io::Result<Self> {
+            let arr = crate::protocol::io::read_bytearray_varshort(r).await?;
+            Ok(ByteArrayVarShort(arr))
+        }
 
+        async fn write(&self, w: &mut impl AsyncWrite + Unpin) -> io::Result<()> {
+            crate::protocol::io::write_bytearray_varshort(w, &self.0).await
+        }
+    }
+}
 
 packet_field! {
     ByteArrayInt(Vec<u8>) {
@@ -0,0 +1,104 @@
+use crate::protocol::io::{read_bool, read_i16_be, read_i32_be, read_u16_be, write_bool, write_i16_be, write_i32_be, write_u16_be};
+use crate::protocol::packets::decoder::MAX_FRAME_SIZE;
+use async_trait::async_trait;
+use tokio::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Координаты и маски одного столбца из `MapChunkBulk`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkMeta {
+    pub x: i32,
+    pub z: i32,
+    pub primary_bitmap: u16,
+    pub add_bitmap: u16,
+}
+
+/// Тело `MapChunkBulk`: число столбцов, длина данных, флаг неба, сжатые zlib данные
+/// всех столбцов подряд и затем их метаданные
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkBulk {
+    pub sky_light: bool,
+    pub data: Vec<u8>,
+    pub columns: Vec<ChunkMeta>,
+}
+
+#[async_trait]
+impl crate::protocol::fields::AsyncReadField for ChunkBulk {
+    async fn read_field<R>(r: &mut R) -> io::Result<Self>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let column_count = read_i16_be(r).await?;
+        let data_length = read_i32_be(r).await?;
+        if column_count < 0 || data_length < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Negative MapChunkBulk length"));
+        }
+        // Длина не может быть больше кадра; буфер растёт по мере чтения, а не по слову сервера
+        if data_length as usize > MAX_FRAME_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("MapChunkBulk data length {} exceeds the frame", data_length),
+            ));
+        }
+        let sky_light = read_bool(r).await?;
+        let mut data = Vec::new();
+        (&mut *r).take(data_length as u64).read_to_end(&mut data).await?;
+        if data.len() != data_length as usize {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated MapChunkBulk data"));
+        }
+
+        let mut columns = Vec::with_capacity(column_count as usize);
+        for _ in 0..column_count {
+            columns.push(ChunkMeta {
+                x: read_i32_be(r).await?,
+                z: read_i32_be(r).await?,
+                primary_bitmap: read_u16_be(r).await?,
+                add_bitmap: read_u16_be(r).await?,
+            });
+        }
+        Ok(ChunkBulk {
+            sky_light,
+            data,
+            columns,
+        })
+    }
+}
+
+#[async_trait]
+impl crate::protocol::fields::AsyncWriteField for ChunkBulk {
+    async fn write_field<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        write_i16_be(w, self.columns.len() as i16).await?;
+        write_i32_be(w, self.data.len() as i32).await?;
+        write_bool(w, self.sky_light).await?;
+        w.write_all(&self.data).await?;
+        for column in &self.columns {
+            write_i32_be(w, column.x).await?;
+            write_i32_be(w, column.z).await?;
+            write_u16_be(w, column.primary_bitmap).await?;
+            write_u16_be(w, column.add_bitmap).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::fields::AsyncReadField;
+
+    #[tokio::test]
+    async fn data_length_is_checked_before_reading() {
+        // Один столбец, 1 ГиБ данных по заявлению сервера
+        let bytes = [0x00, 0x01, 0x40, 0x00, 0x00, 0x00, 0x01, 0xAA];
+        let err = ChunkBulk::read_field(&mut &bytes[..]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Длина в пределах кадра, но данных меньше заявленного
+        let bytes = [0x00, 0x01, 0x00, 0x00, 0x10, 0x00, 0x01, 0xAA];
+        let err = ChunkBulk::read_field(&mut &bytes[..]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,28 @@
+use crate::packet_field;
+use crate::protocol::io::{read_i32_be, read_u8_be, write_i32_be, write_u8_be};
+use tokio::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+packet_field! {
+    /// Список id сущностей из `DestroyEntities`: байт-длина и int на каждый id
+    EntityIds(Vec<i32>) {
+        async fn read(r: &mut impl AsyncRead + Unpin) -> io::Result<Self> {
+            let count = read_u8_be(r).await?;
+            let mut ids = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                ids.push(read_i32_be(r).await?);
+            }
+            Ok(EntityIds(ids))
+        }
+
+        async fn write(&self, w: &mut impl AsyncWrite + Unpin) -> io::Result<()> {
+            let count = u8::try_from(self.0.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many entity ids"))?;
+            write_u8_be(w, count).await?;
+            for id in &self.0 {
+                write_i32_be(w, *id).await?;
+            }
+            Ok(())
+        }
+    }
+}
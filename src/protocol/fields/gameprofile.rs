@@ -10,11 +10,7 @@ packet_field! {
         async fn read(r: &mut impl AsyncRead + Unpin) -> io::Result<Self> {
             let name = VarString::read(r).await?;
             let value = VarString::read(r).await?;
-            let signature = if let Ok(sig) = VarString::read(r).await {
-                Some(sig)
-            } else {
-                None
-            };
+            let signature = VarString::read(r).await.ok();
 
             Ok(Property((name, value, signature)))
         }
@@ -44,13 +40,9 @@ packet_field! {
             for _ in 0..property_count {
                 let name = VarString::read(r).await?;
                 let value = VarString::read(r).await?;
-                let signature = if let Ok(sig) = VarString::read(r).await {
-                    Some(sig)
-                } else {
-                    None
-                };
+                let signature = VarString::read(r).await.ok();
 
-                properties.push(Property { 0: (name, value, signature) });
+                properties.push(Property((name, value, signature)));
             }
 
             Ok(GameProfile((Uuid(uuid), name, properties)))
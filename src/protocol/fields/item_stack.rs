@@ -1,13 +1,62 @@
 use crate::protocol::fields::{Byte, Short};
+use crate::protocol::fml::IdRegistry;
 use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use nbt::{Blob, NBTRead, NBTWrite};
+use std::io::{Read, Write};
 use tokio::io;
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ItemStack {
     pub item_id: i16,
     pub count: u8,
     pub damage: i16,
+    /// Тег предмета в несжатом виде. На проводе — short-длина и gzip, `-1` — тега нет.
+    pub nbt: Option<Vec<u8>>,
+}
+
+impl ItemStack {
+    pub fn new(item_id: i16, count: u8, damage: i16) -> Self {
+        Self {
+            item_id,
+            count,
+            damage,
+            nbt: None,
+        }
+    }
+
+    /// Пустой слот
+    pub fn empty() -> Self {
+        Self::new(-1, 0, 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.item_id == -1
+    }
+
+    /// Имя предмета по таблице id сервера (см. `Connection::registry`)
+    pub fn name<'a>(&self, registry: &'a IdRegistry) -> Option<&'a str> {
+        if self.is_empty() {
+            return None;
+        }
+        registry.item_name(self.item_id as i32)
+    }
+
+    /// Разобранный тег предмета
+    pub fn tag(&self) -> io::Result<Option<Blob>> {
+        self.nbt
+            .as_ref()
+            .map(|bytes| Blob::from_bytes(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())))
+            .transpose()
+    }
+
+    pub fn with_tag(mut self, tag: &Blob) -> io::Result<Self> {
+        self.nbt = Some(tag.bytes().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?);
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -18,20 +67,26 @@ impl crate::protocol::fields::AsyncReadField for ItemStack {
     {
         let id = Short::read_field(r).await?.0;
         if id == -1 {
-            Ok(ItemStack {
-                item_id: -1,
-                count: 0,
-                damage: 0,
-            })
-        } else {
-            let count = Byte::read_field(r).await?.0;
-            let damage = Short::read_field(r).await?.0;
-            Ok(ItemStack {
-                item_id: id,
-                count,
-                damage,
-            })
+            return Ok(ItemStack::empty());
         }
+        let count = Byte::read_field(r).await?.0;
+        let damage = Short::read_field(r).await?.0;
+        let nbt_length = Short::read_field(r).await?.0;
+        let nbt = if nbt_length < 0 {
+            None
+        } else {
+            let mut compressed = vec![0u8; nbt_length as usize];
+            r.read_exact(&mut compressed).await?;
+            let mut nbt = Vec::new();
+            GzDecoder::new(&compressed[..]).read_to_end(&mut nbt)?;
+            Some(nbt)
+        };
+        Ok(ItemStack {
+            item_id: id,
+            count,
+            damage,
+            nbt,
+        })
     }
 }
 
@@ -42,11 +97,55 @@ impl crate::protocol::fields::AsyncWriteField for ItemStack {
         W: AsyncWrite + Unpin + Send,
     {
         if self.item_id == -1 {
-            Short(-1).write_field(w).await
-        } else {
-            Short(self.item_id).write_field(w).await?;
-            Byte(self.count).write_field(w).await?;
-            Short(self.damage).write_field(w).await
+            return Short(-1).write_field(w).await;
         }
+        Short(self.item_id).write_field(w).await?;
+        Byte(self.count).write_field(w).await?;
+        Short(self.damage).write_field(w).await?;
+        match &self.nbt {
+            None => Short(-1).write_field(w).await,
+            Some(nbt) => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(nbt)?;
+                let compressed = encoder.finish()?;
+                let length = i16::try_from(compressed.len())
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Item NBT is too long"))?;
+                Short(length).write_field(w).await?;
+                w.write_all(&compressed).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::fields::{AsyncReadField, AsyncWriteField};
+    use nbt::Tag;
+
+    #[tokio::test]
+    async fn nbt_round_trip() {
+        let mut tag = Blob::new();
+        tag.insert("pages", Tag::List(vec![Tag::String("hello".to_string())]));
+        let stack = ItemStack::new(386, 1, 0).with_tag(&tag).unwrap();
+
+        let mut buf = Vec::new();
+        stack.write_field(&mut buf).await.unwrap();
+        // id, count, damage, затем длина сжатого тега и gzip-заголовок
+        assert_eq!(&buf[..5], [0x01, 0x82, 1, 0, 0]);
+        assert_eq!(&buf[7..9], [0x1f, 0x8b]);
+
+        let read = ItemStack::read_field(&mut &buf[..]).await.unwrap();
+        assert_eq!(read, stack);
+        let pages = read.tag().unwrap().unwrap().elements.remove("pages");
+        assert_eq!(pages, Some(Tag::List(vec![Tag::String("hello".to_string())])));
+    }
+
+    #[tokio::test]
+    async fn stack_without_tag() {
+        let bytes = [0x00, 0x01, 64, 0x00, 0x00, 0xFF, 0xFF];
+        let stack = ItemStack::read_field(&mut &bytes[..]).await.unwrap();
+        assert_eq!(stack, ItemStack::new(1, 64, 0));
+        assert!(ItemStack::read_field(&mut &[0xFF, 0xFF][..]).await.unwrap().is_empty());
     }
 }
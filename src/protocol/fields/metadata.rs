@@ -0,0 +1,126 @@
+use crate::protocol::fields::{AsyncReadField, AsyncWriteField, ItemStack};
+use crate::protocol::io::{
+    read_f32_be, read_i16_be, read_i32_be, read_i8, read_u8_be, read_varstring, write_f32_be, write_i16_be,
+    write_i32_be, write_i8, write_u8_be, write_varstring,
+};
+use async_trait::async_trait;
+use tokio::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Конец списка метаданных
+const METADATA_END: u8 = 0x7F;
+
+/// Значение метаданных сущности
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Float(f32),
+    String(String),
+    Item(ItemStack),
+    /// Координаты блока, три int
+    Position(i32, i32, i32),
+}
+
+impl MetadataValue {
+    fn type_id(&self) -> u8 {
+        match self {
+            MetadataValue::Byte(_) => 0,
+            MetadataValue::Short(_) => 1,
+            MetadataValue::Int(_) => 2,
+            MetadataValue::Float(_) => 3,
+            MetadataValue::String(_) => 4,
+            MetadataValue::Item(_) => 5,
+            MetadataValue::Position(..) => 6,
+        }
+    }
+}
+
+/// Метаданные сущности: записи с байтом `тип << 5 | индекс` перед значением,
+/// список завершается `0x7F`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata(pub Vec<(u8, MetadataValue)>);
+
+#[async_trait]
+impl AsyncReadField for Metadata {
+    async fn read_field<R>(r: &mut R) -> io::Result<Self>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let mut entries = Vec::new();
+        loop {
+            let header = read_u8_be(r).await?;
+            if header == METADATA_END {
+                break;
+            }
+            let value = match header >> 5 {
+                0 => MetadataValue::Byte(read_i8(r).await?),
+                1 => MetadataValue::Short(read_i16_be(r).await?),
+                2 => MetadataValue::Int(read_i32_be(r).await?),
+                3 => MetadataValue::Float(read_f32_be(r).await?),
+                4 => MetadataValue::String(read_varstring(r).await?),
+                5 => MetadataValue::Item(ItemStack::read_field(r).await?),
+                6 => MetadataValue::Position(read_i32_be(r).await?, read_i32_be(r).await?, read_i32_be(r).await?),
+                other => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown metadata type: {}", other),
+                    ))
+                }
+            };
+            entries.push((header & 0x1F, value));
+        }
+        Ok(Metadata(entries))
+    }
+}
+
+#[async_trait]
+impl AsyncWriteField for Metadata {
+    async fn write_field<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        for (index, value) in &self.0 {
+            write_u8_be(w, value.type_id() << 5 | index & 0x1F).await?;
+            match value {
+                MetadataValue::Byte(v) => write_i8(w, *v).await?,
+                MetadataValue::Short(v) => write_i16_be(w, *v).await?,
+                MetadataValue::Int(v) => write_i32_be(w, *v).await?,
+                MetadataValue::Float(v) => write_f32_be(w, *v).await?,
+                MetadataValue::String(v) => write_varstring(w, v).await?,
+                MetadataValue::Item(v) => v.write_field(w).await?,
+                MetadataValue::Position(x, y, z) => {
+                    write_i32_be(w, *x).await?;
+                    write_i32_be(w, *y).await?;
+                    write_i32_be(w, *z).await?;
+                }
+            }
+        }
+        write_u8_be(w, METADATA_END).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn metadata_round_trip() {
+        // Флаги (byte 0), здоровье (float 6) и имя (string 10)
+        let bytes = [0x00, 0x02, 0x66, 0x41, 0xA0, 0x00, 0x00, 0x8A, 0x03, b'B', b'o', b'b', 0x7F];
+        let metadata = Metadata::read_field(&mut &bytes[..]).await.unwrap();
+        assert_eq!(
+            metadata.0,
+            [
+                (0, MetadataValue::Byte(2)),
+                (6, MetadataValue::Float(20.0)),
+                (10, MetadataValue::String("Bob".to_string())),
+            ]
+        );
+
+        let mut buf = Vec::new();
+        metadata.write_field(&mut buf).await.unwrap();
+        assert_eq!(buf, bytes);
+    }
+}
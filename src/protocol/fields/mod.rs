@@ -16,15 +16,20 @@ pub trait AsyncWriteField {
         W: AsyncWrite + Unpin + Send;
 }
 
+pub mod block_records;
 pub mod boolean;
 pub mod byte;
 pub mod byte_array;
+pub mod chunk_bulk;
 pub mod double;
+pub mod entity_ids;
 pub mod entity_property;
 pub mod float;
 pub mod int;
 pub mod item_stack;
 pub mod long;
+pub mod metadata;
+pub mod object_data;
 pub mod properties;
 pub mod short;
 pub mod ushort;
@@ -37,18 +42,24 @@ pub mod nbt;
 pub mod unimplemented;
 
 pub use boolean::Boolean;
+pub use block_records::{BlockRecord, BlockRecords, ExplosionRecord, ExplosionRecords};
 pub use byte::Byte;
 pub use byte_array::*;
+pub use chunk_bulk::{ChunkBulk, ChunkMeta};
 pub use double::Double;
+pub use entity_ids::EntityIds;
 pub use entity_property::EntityProperty;
 pub use float::Float;
 pub use gameprofile::GameProfile;
 pub use int::Int;
 pub use item_stack::ItemStack;
 pub use long::Long;
+pub use metadata::{Metadata, MetadataValue};
+pub use object_data::ObjectData;
 pub use properties::Properties;
 pub use short::Short;
 pub use unimplemented::Unimplemented;
 pub use ushort::UShort;
 pub use varint::VarInt;
 pub use varstring::VarString;
+pub use vec::VecVarInt;
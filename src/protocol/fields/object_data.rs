@@ -0,0 +1,45 @@
+use crate::protocol::io::{read_i16_be, read_i32_be, write_i16_be, write_i32_be};
+use async_trait::async_trait;
+use tokio::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Хвост `SpawnObject`: int-данные объекта (у стрелы — id стрелявшего, у блока — id и meta)
+/// и, только если они положительны, скорость тремя short
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ObjectData {
+    pub data: i32,
+    pub velocity: Option<(i16, i16, i16)>,
+}
+
+#[async_trait]
+impl crate::protocol::fields::AsyncReadField for ObjectData {
+    async fn read_field<R>(r: &mut R) -> io::Result<Self>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let data = read_i32_be(r).await?;
+        let velocity = if data > 0 {
+            Some((read_i16_be(r).await?, read_i16_be(r).await?, read_i16_be(r).await?))
+        } else {
+            None
+        };
+        Ok(ObjectData { data, velocity })
+    }
+}
+
+#[async_trait]
+impl crate::protocol::fields::AsyncWriteField for ObjectData {
+    async fn write_field<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        write_i32_be(w, self.data).await?;
+        if self.data > 0 {
+            let (x, y, z) = self.velocity.unwrap_or_default();
+            write_i16_be(w, x).await?;
+            write_i16_be(w, y).await?;
+            write_i16_be(w, z).await?;
+        }
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use crate::protocol::fields::{AsyncReadField, AsyncWriteField, VarInt, VarString
 use std::collections::HashMap;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Properties(pub HashMap<<VarString as std::ops::Deref>::Target, VarInt>);
 
 impl Properties {
@@ -38,3 +38,52 @@ where
         Ok(())
     }
 }
+
+/// Список с префиксом длины VarInt (обычный `Vec<T>` в протоколе префиксуется Short).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VecVarInt<T>(pub Vec<T>);
+
+impl<T> std::ops::Deref for VecVarInt<T> {
+    type Target = Vec<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> crate::protocol::fields::AsyncReadField for VecVarInt<T>
+where
+    T: crate::protocol::fields::AsyncReadField + Send,
+{
+    async fn read_field<R>(r: &mut R) -> io::Result<Self>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let count_val = crate::protocol::fields::VarInt::read_field(r).await?.0;
+        let count = if count_val < 0 { 0 } else { count_val as usize };
+        let mut vec = Vec::with_capacity(count);
+        for _ in 0..count {
+            vec.push(T::read_field(r).await?);
+        }
+        Ok(VecVarInt(vec))
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> crate::protocol::fields::AsyncWriteField for VecVarInt<T>
+where
+    T: crate::protocol::fields::AsyncWriteField + Sync,
+{
+    async fn write_field<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        crate::protocol::fields::VarInt(self.0.len() as i32)
+            .write_field(w)
+            .await?;
+        for item in &self.0 {
+            item.write_field(w).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use crate::protocol::fml::{
+    FmlMessage, FmlMod, ModIdData, ModProfile, FML_HANDSHAKE_CHANNEL, FML_MULTIPART_CHANNEL, FML_PROTOCOL_VERSION,
+};
+use tokio::io;
+
+/// Каналы, которые Forge-клиент без модов объявляет серверу (`NetworkRegistry.channelNamesFor`)
+pub const FML_CHANNELS: [&str; 4] = [FML_HANDSHAKE_CHANNEL, "FML", FML_MULTIPART_CHANNEL, "FORGE"];
+
+/// Ответ клиента на сообщение рукопожатия
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FmlReply {
+    /// `REGISTER` со списком каналов клиента, как `makeCustomChannelRegistration`
+    Register(Vec<String>),
+    /// Сообщение в `FML|HS`
+    Message(FmlMessage),
+}
+
+/// Состояния клиента, как `FMLHandshakeClientState` в 1.7.10.
+/// Порядковый номер уходит в `HandshakeAck.phase`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FmlClientState {
+    Start = 0,
+    Hello = 1,
+    WaitingServerData = 2,
+    WaitingServerComplete = 3,
+    PendingComplete = 4,
+    Complete = 5,
+    Done = 6,
+    Error = 7,
+}
+
+impl FmlClientState {
+    pub fn ordinal(self) -> i8 {
+        self as i8
+    }
+}
+
+/// Клиентская сторона рукопожатия FML:
+/// ServerHello -> ClientHello + ModList -> ModList сервера -> ModIdData -> два HandshakeAck сервера.
+/// Каждое сообщение сервера продвигает состояние и возвращает то, что нужно ответить.
+#[derive(Debug, Clone)]
+pub struct FmlHandshake {
+    state: FmlClientState,
+    /// Что клиент сообщает серверу о своих модах
+    pub mods: Vec<FmlMod>,
+    pub server_mods: Option<Vec<FmlMod>>,
+    pub mod_id_data: Option<ModIdData>,
+    pub override_dimension: Option<i32>,
+    /// Каналы, которые клиент объявляет серверу перед `ClientHello`
+    pub channels: Vec<String>,
+}
+
+impl Default for FmlHandshake {
+    /// Голый Forge 1.7.10 без сторонних модов
+    fn default() -> Self {
+        Self::new(vec![
+            FmlMod::new("mcp", "9.05"),
+            FmlMod::new("FML", "7.10.99.99"),
+            FmlMod::new("Forge", "10.13.4.1614"),
+        ])
+    }
+}
+
+impl FmlHandshake {
+    pub fn new(mods: Vec<FmlMod>) -> Self {
+        Self {
+            state: FmlClientState::Start,
+            mods,
+            server_mods: None,
+            mod_id_data: None,
+            override_dimension: None,
+            channels: FML_CHANNELS.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    pub fn with_profile(profile: &ModProfile) -> Self {
+        Self::new(profile.mods.clone())
+    }
+
+    pub fn state(&self) -> FmlClientState {
+        self.state
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == FmlClientState::Done
+    }
+
+    /// Обрабатывает сообщение сервера и возвращает ответы клиента в порядке отправки.
+    /// Сообщение не к месту переводит рукопожатие в `Error` и возвращает ошибку.
+    pub fn accept(&mut self, message: FmlMessage) -> io::Result<Vec<FmlReply>> {
+        use FmlClientState::*;
+
+        let (next, replies) = match (self.state, message) {
+            (_, FmlMessage::HandshakeReset) => {
+                let channels = std::mem::take(&mut self.channels);
+                *self = Self::new(std::mem::take(&mut self.mods));
+                self.channels = channels;
+                return Ok(Vec::new());
+            }
+            (Start | Hello, FmlMessage::ServerHello { override_dimension, .. }) => {
+                self.override_dimension = override_dimension;
+                let replies = vec![
+                    FmlReply::Register(self.channels.clone()),
+                    FmlReply::Message(FmlMessage::ClientHello {
+                        protocol_version: FML_PROTOCOL_VERSION,
+                    }),
+                    FmlReply::Message(FmlMessage::ModList(self.mods.clone())),
+                ];
+                (WaitingServerData, replies)
+            }
+            (WaitingServerData, FmlMessage::ModList(mods)) => {
+                self.server_mods = Some(mods);
+                (WaitingServerComplete, vec![self.ack()])
+            }
+            (WaitingServerComplete, FmlMessage::ModIdData(data)) => {
+                self.mod_id_data = Some(data);
+                (PendingComplete, vec![self.ack()])
+            }
+            (PendingComplete, FmlMessage::HandshakeAck { .. }) => (Complete, vec![self.ack()]),
+            (Complete, FmlMessage::HandshakeAck { .. }) => (Done, vec![self.ack()]),
+            (state, message) => {
+                self.state = Error;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unexpected FML message {:?} in state {:?}", message, state),
+                ));
+            }
+        };
+        self.state = next;
+        Ok(replies)
+    }
+
+    /// Подтверждение текущей фазы: клиент шлёт номер состояния, из которого уходит
+    fn ack(&self) -> FmlReply {
+        FmlReply::Message(FmlMessage::HandshakeAck {
+            phase: self.state.ordinal(),
+        })
+    }
+}
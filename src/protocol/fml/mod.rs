@@ -0,0 +1,241 @@
+//! Forge Mod Loader 1.7.10: сообщения канала `FML|HS` и клиентская сторона рукопожатия.
+
+use crate::protocol::io::{
+    read_i32_be, read_i8, read_varint, read_varstring, write_i32_be, write_i8, write_varint, write_varstring,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io;
+
+pub mod handshake;
+pub mod multipart;
+pub mod profile;
+pub mod registry;
+pub use handshake::{FmlClientState, FmlHandshake, FmlReply, FML_CHANNELS};
+pub use multipart::{MultipartAssembler, FML_MULTIPART_CHANNEL};
+pub use profile::{ModInfo, ModProfile};
+pub use registry::IdRegistry;
+
+pub const FML_HANDSHAKE_CHANNEL: &str = "FML|HS";
+/// Версия протокола FML в 1.7.10 (`NetworkRegistry.FML_PROTOCOL`)
+pub const FML_PROTOCOL_VERSION: i8 = 2;
+
+const SERVER_HELLO: i8 = 0;
+const CLIENT_HELLO: i8 = 1;
+const MOD_LIST: i8 = 2;
+const MOD_ID_DATA: i8 = 3;
+const HANDSHAKE_ACK: i8 = -1;
+const HANDSHAKE_RESET: i8 = -2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FmlMod {
+    /// В файлах профилей и в `modinfo` статуса поле называется `modid`
+    #[serde(rename = "modid", alias = "mod_id")]
+    pub mod_id: String,
+    pub version: String,
+}
+
+impl FmlMod {
+    pub fn new(mod_id: &str, version: &str) -> Self {
+        Self {
+            mod_id: mod_id.to_string(),
+            version: version.to_string(),
+        }
+    }
+}
+
+/// Таблица числовых id блоков и предметов сервера (`ModIdData`, она же ItemData)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModIdData {
+    /// Имя (с префиксом `\u{1}` для блоков и `\u{2}` для предметов) и id
+    pub ids: Vec<(String, i32)>,
+    pub block_substitutions: Vec<String>,
+    pub item_substitutions: Vec<String>,
+}
+
+/// Сообщение канала `FML|HS`. Первый байт полезной нагрузки — дискриминатор.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FmlMessage {
+    ServerHello {
+        protocol_version: i8,
+        /// Начиная с протокола 2 сервер сообщает измерение, в котором появится игрок
+        override_dimension: Option<i32>,
+    },
+    ClientHello {
+        protocol_version: i8,
+    },
+    ModList(Vec<FmlMod>),
+    ModIdData(ModIdData),
+    /// `phase` — порядковый номер состояния отправителя
+    HandshakeAck {
+        phase: i8,
+    },
+    HandshakeReset,
+}
+
+impl FmlMessage {
+    pub async fn decode(mut data: &[u8]) -> io::Result<Self> {
+        let r = &mut data;
+        let message = match read_i8(r).await? {
+            SERVER_HELLO => {
+                let protocol_version = read_i8(r).await?;
+                let override_dimension = if protocol_version > 1 {
+                    Some(read_i32_be(r).await?)
+                } else {
+                    None
+                };
+                FmlMessage::ServerHello {
+                    protocol_version,
+                    override_dimension,
+                }
+            }
+            CLIENT_HELLO => FmlMessage::ClientHello {
+                protocol_version: read_i8(r).await?,
+            },
+            MOD_LIST => {
+                let count = read_count(r).await?;
+                let mut mods = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let mod_id = read_varstring(r).await?;
+                    let version = read_varstring(r).await?;
+                    mods.push(FmlMod { mod_id, version });
+                }
+                FmlMessage::ModList(mods)
+            }
+            MOD_ID_DATA => {
+                let count = read_count(r).await?;
+                let mut ids = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let name = read_varstring(r).await?;
+                    ids.push((name, read_varint(r).await?));
+                }
+                // Старые сборки FML замены не присылают
+                let (block_substitutions, item_substitutions) = if r.is_empty() {
+                    (Vec::new(), Vec::new())
+                } else {
+                    (read_strings(r).await?, read_strings(r).await?)
+                };
+                FmlMessage::ModIdData(ModIdData {
+                    ids,
+                    block_substitutions,
+                    item_substitutions,
+                })
+            }
+            HANDSHAKE_ACK => FmlMessage::HandshakeAck {
+                phase: read_i8(r).await?,
+            },
+            HANDSHAKE_RESET => FmlMessage::HandshakeReset,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown FML handshake discriminator: {}", other),
+                ))
+            }
+        };
+        Ok(message)
+    }
+
+    pub async fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match self {
+            FmlMessage::ServerHello {
+                protocol_version,
+                override_dimension,
+            } => {
+                write_i8(&mut buf, SERVER_HELLO).await?;
+                write_i8(&mut buf, *protocol_version).await?;
+                if let Some(dimension) = override_dimension {
+                    write_i32_be(&mut buf, *dimension).await?;
+                }
+            }
+            FmlMessage::ClientHello { protocol_version } => {
+                write_i8(&mut buf, CLIENT_HELLO).await?;
+                write_i8(&mut buf, *protocol_version).await?;
+            }
+            FmlMessage::ModList(mods) => {
+                write_i8(&mut buf, MOD_LIST).await?;
+                write_varint(&mut buf, mods.len() as i32).await?;
+                for m in mods {
+                    write_varstring(&mut buf, &m.mod_id).await?;
+                    write_varstring(&mut buf, &m.version).await?;
+                }
+            }
+            FmlMessage::ModIdData(data) => {
+                write_i8(&mut buf, MOD_ID_DATA).await?;
+                write_varint(&mut buf, data.ids.len() as i32).await?;
+                for (name, id) in &data.ids {
+                    write_varstring(&mut buf, name).await?;
+                    write_varint(&mut buf, *id).await?;
+                }
+                write_strings(&mut buf, &data.block_substitutions).await?;
+                write_strings(&mut buf, &data.item_substitutions).await?;
+            }
+            FmlMessage::HandshakeAck { phase } => {
+                write_i8(&mut buf, HANDSHAKE_ACK).await?;
+                write_i8(&mut buf, *phase).await?;
+            }
+            FmlMessage::HandshakeReset => write_i8(&mut buf, HANDSHAKE_RESET).await?,
+        }
+        Ok(buf)
+    }
+}
+
+async fn read_count(r: &mut &[u8]) -> io::Result<usize> {
+    let count = read_varint(r).await?;
+    usize::try_from(count).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Negative FML list length"))
+}
+
+async fn read_strings(r: &mut &[u8]) -> io::Result<Vec<String>> {
+    let count = read_count(r).await?;
+    let mut strings = Vec::with_capacity(count);
+    for _ in 0..count {
+        strings.push(read_varstring(r).await?);
+    }
+    Ok(strings)
+}
+
+async fn write_strings(w: &mut Vec<u8>, strings: &[String]) -> io::Result<()> {
+    write_varint(w, strings.len() as i32).await?;
+    for s in strings {
+        write_varstring(w, s).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn messages_round_trip() {
+        let messages = [
+            FmlMessage::ServerHello {
+                protocol_version: 2,
+                override_dimension: Some(-1),
+            },
+            FmlMessage::ClientHello { protocol_version: 2 },
+            FmlMessage::ModList(vec![FmlMod::new("FML", "7.10.99.99"), FmlMod::new("IC2", "2.2.827")]),
+            FmlMessage::ModIdData(ModIdData {
+                ids: vec![("\u{1}minecraft:stone".to_string(), 1), ("\u{2}IC2:itemCable".to_string(), 4097)],
+                block_substitutions: Vec::new(),
+                item_substitutions: vec!["minecraft:dirt".to_string()],
+            }),
+            FmlMessage::HandshakeAck { phase: 5 },
+            FmlMessage::HandshakeReset,
+        ];
+        for message in messages {
+            let encoded = message.encode().await.unwrap();
+            assert_eq!(FmlMessage::decode(&encoded).await.unwrap(), message);
+        }
+    }
+
+    #[tokio::test]
+    async fn mod_id_data_without_substitutions() {
+        // count=1, "\u1a" -> 5
+        let data = [MOD_ID_DATA as u8, 1, 2, 1, b'a', 5];
+        let FmlMessage::ModIdData(decoded) = FmlMessage::decode(&data).await.unwrap() else {
+            panic!("expected ModIdData");
+        };
+        assert_eq!(decoded.ids, vec![("\u{1}a".to_string(), 5)]);
+        assert!(decoded.block_substitutions.is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+//! `FML|MP`: сообщения длиннее лимита `CustomPayload` FML режет на части.
+//!
+//! Сначала идёт преамбула: канал (VarString), число частей (byte) и полная длина (int).
+//! Затем сами части: номер части (byte) и кусок данных.
+
+use crate::protocol::io::{read_i32_be, read_u8_be, read_varstring, write_i32_be, write_u8_be, write_varstring};
+use crate::protocol::packets::decoder::MAX_FRAME_SIZE;
+use tokio::io::{self, AsyncReadExt};
+
+pub const FML_MULTIPART_CHANNEL: &str = "FML|MP";
+/// Данные `CustomPayload` в 1.7.10 предваряются short-длиной
+pub const MAX_PAYLOAD_SIZE: usize = i16::MAX as usize;
+/// Сколько данных помещается в одну часть рядом с её номером
+const PART_DATA_SIZE: usize = MAX_PAYLOAD_SIZE - 1;
+const MAX_PARTS: usize = u8::MAX as usize;
+
+/// Режет сообщение для `channel` на данные `FML|MP`: преамбула и части
+pub async fn split(channel: &str, data: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+    let parts = data.len().div_ceil(PART_DATA_SIZE);
+    if parts > MAX_PARTS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Payload for {} is too long for FML|MP: {} bytes", channel, data.len()),
+        ));
+    }
+
+    let mut preamble = Vec::new();
+    write_varstring(&mut preamble, channel).await?;
+    write_u8_be(&mut preamble, parts as u8).await?;
+    write_i32_be(&mut preamble, data.len() as i32).await?;
+
+    let mut messages = vec![preamble];
+    for (index, chunk) in data.chunks(PART_DATA_SIZE).enumerate() {
+        let mut part = Vec::with_capacity(chunk.len() + 1);
+        part.push(index as u8);
+        part.extend_from_slice(chunk);
+        messages.push(part);
+    }
+    Ok(messages)
+}
+
+#[derive(Debug)]
+struct Pending {
+    channel: String,
+    parts: u8,
+    received: u8,
+    length: usize,
+    data: Vec<u8>,
+}
+
+/// Собирает сообщения `FML|MP` обратно. Части приходят по порядку, одно сообщение за раз.
+#[derive(Debug, Default)]
+pub struct MultipartAssembler {
+    pending: Option<Pending>,
+}
+
+impl MultipartAssembler {
+    pub fn is_assembling(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Принимает данные очередного `FML|MP`. Возвращает канал и данные,
+    /// когда пришла последняя часть.
+    pub async fn accept(&mut self, data: &[u8]) -> io::Result<Option<(String, Vec<u8>)>> {
+        let Some(pending) = self.pending.as_mut() else {
+            self.pending = Some(read_preamble(data).await?);
+            return Ok(None);
+        };
+
+        let (&index, chunk) = data
+            .split_first()
+            .ok_or_else(|| invalid("Empty FML|MP part".to_string()))?;
+        if index != pending.received {
+            let message = format!(
+                "FML|MP part {} for {} arrived, expected {}",
+                index, pending.channel, pending.received
+            );
+            self.pending = None;
+            return Err(invalid(message));
+        }
+        pending.data.extend_from_slice(chunk);
+        pending.received += 1;
+        if pending.data.len() > pending.length {
+            let message = format!("FML|MP payload for {} exceeds {} bytes", pending.channel, pending.length);
+            self.pending = None;
+            return Err(invalid(message));
+        }
+        if pending.received < pending.parts {
+            return Ok(None);
+        }
+
+        let pending = self.pending.take().unwrap();
+        if pending.data.len() != pending.length {
+            return Err(invalid(format!(
+                "FML|MP payload for {} is {} bytes, expected {}",
+                pending.channel,
+                pending.data.len(),
+                pending.length
+            )));
+        }
+        Ok(Some((pending.channel, pending.data)))
+    }
+}
+
+async fn read_preamble(mut data: &[u8]) -> io::Result<Pending> {
+    let r = &mut data;
+    let channel = read_varstring(r).await?;
+    let parts = read_u8_be(r).await?;
+    let length = read_i32_be(r).await?;
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).await?;
+    if parts == 0 || length < 0 || !rest.is_empty() {
+        return Err(invalid(format!("Malformed FML|MP preamble for {}", channel)));
+    }
+    // Длине из преамбулы не верим: буфер растёт по мере прихода частей. Части от сервера
+    // приходят в S3F с varshort-длиной и ограничены только размером кадра.
+    if length as usize > parts as usize * MAX_FRAME_SIZE {
+        return Err(invalid(format!(
+            "FML|MP preamble for {} claims {} bytes in {} parts",
+            channel, length, parts
+        )));
+    }
+    Ok(Pending {
+        channel,
+        parts,
+        received: 0,
+        length: length as usize,
+        data: Vec::new(),
+    })
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn split_and_reassemble() {
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 253) as u8).collect();
+        let messages = split("ConfigSync", &data).await.unwrap();
+        // Преамбула и четыре части
+        assert_eq!(messages.len(), 5);
+        assert!(messages.iter().all(|m| m.len() <= MAX_PAYLOAD_SIZE));
+
+        let mut assembler = MultipartAssembler::default();
+        let mut result = None;
+        for message in &messages {
+            assert!(result.is_none());
+            result = assembler.accept(message).await.unwrap();
+        }
+        assert_eq!(result, Some(("ConfigSync".to_string(), data)));
+        assert!(!assembler.is_assembling());
+    }
+
+    #[tokio::test]
+    async fn reassembles_parts_larger_than_client_limit() {
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 241) as u8).collect();
+        let mut preamble = Vec::new();
+        write_varstring(&mut preamble, "ConfigSync").await.unwrap();
+        write_u8_be(&mut preamble, 2).await.unwrap();
+        write_i32_be(&mut preamble, data.len() as i32).await.unwrap();
+
+        let mut assembler = MultipartAssembler::default();
+        assert_eq!(assembler.accept(&preamble).await.unwrap(), None);
+        // Forge-сервер режет по 50000 байт: больше, чем влезает в C17
+        let mut result = None;
+        for (index, chunk) in data.chunks(50_000).enumerate() {
+            assert!(chunk.len() > MAX_PAYLOAD_SIZE);
+            let mut part = vec![index as u8];
+            part.extend_from_slice(chunk);
+            result = assembler.accept(&part).await.unwrap();
+        }
+        assert_eq!(result, Some(("ConfigSync".to_string(), data)));
+    }
+
+    #[tokio::test]
+    async fn rejects_length_beyond_parts() {
+        let mut preamble = Vec::new();
+        write_varstring(&mut preamble, "ConfigSync").await.unwrap();
+        write_u8_be(&mut preamble, 2).await.unwrap();
+        write_i32_be(&mut preamble, i32::MAX).await.unwrap();
+        let mut assembler = MultipartAssembler::default();
+        assert!(assembler.accept(&preamble).await.is_err());
+        assert!(!assembler.is_assembling());
+    }
+
+    #[tokio::test]
+    async fn rejects_parts_out_of_order() {
+        let data = vec![7u8; 70_000];
+        let messages = split("ConfigSync", &data).await.unwrap();
+        let mut assembler = MultipartAssembler::default();
+        assembler.accept(&messages[0]).await.unwrap();
+        assert!(assembler.accept(&messages[2]).await.is_err());
+        assert!(!assembler.is_assembling());
+    }
+}
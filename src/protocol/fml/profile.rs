@@ -0,0 +1,104 @@
+use crate::protocol::fml::FmlMod;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+/// `modinfo` из ответа на статус-запрос Forge-сервера
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ModInfo {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "modList", default)]
+    pub mod_list: Vec<FmlMod>,
+}
+
+/// Набор модов, который бот называет серверу в `ModList` рукопожатия FML.
+/// У каждой сборки свой профиль; файл — JSON или TOML со списком `mods`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModProfile {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub mods: Vec<FmlMod>,
+}
+
+impl ModProfile {
+    pub fn new(mods: Vec<FmlMod>) -> Self {
+        Self { name: None, mods }
+    }
+
+    /// Загружает профиль из файла; формат выбирается по расширению (`.toml`, иначе JSON)
+    pub async fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = tokio::fs::read_to_string(path).await?;
+        let is_toml = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        if is_toml {
+            Self::from_toml(&text)
+        } else {
+            Self::from_json(&text)
+        }
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        serde_json::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Моды, которые сервер перечислил в статусе. Для не-Forge сервера — `None`.
+    pub fn from_modinfo(modinfo: &ModInfo) -> Option<Self> {
+        (modinfo.kind == "FML").then(|| Self::new(modinfo.mod_list.clone()))
+    }
+
+    /// Зеркало `ModList` сервера из прошлого рукопожатия (`FmlHandshake::server_mods`)
+    pub fn from_server_mod_list(mods: &[FmlMod]) -> Self {
+        Self::new(mods.to_vec())
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_and_toml_profiles_agree() {
+        let json = r#"{ "name": "industrial", "mods": [
+            { "modid": "FML", "version": "7.10.99.99" },
+            { "modid": "IC2", "version": "2.2.827-experimental" }
+        ] }"#;
+        let toml = r#"
+            name = "industrial"
+
+            [[mods]]
+            modid = "FML"
+            version = "7.10.99.99"
+
+            [[mods]]
+            modid = "IC2"
+            version = "2.2.827-experimental"
+        "#;
+        let from_json = ModProfile::from_json(json).unwrap();
+        assert_eq!(from_json, ModProfile::from_toml(toml).unwrap());
+        assert_eq!(from_json.mods[1], FmlMod::new("IC2", "2.2.827-experimental"));
+    }
+
+    #[test]
+    fn profile_from_status_modinfo() {
+        let status = r#"{ "type": "FML", "modList": [{ "modid": "mcp", "version": "9.05" }] }"#;
+        let modinfo: ModInfo = serde_json::from_str(status).unwrap();
+        let profile = ModProfile::from_modinfo(&modinfo).unwrap();
+        assert_eq!(profile.mods, vec![FmlMod::new("mcp", "9.05")]);
+
+        let vanilla: ModInfo = serde_json::from_str(r#"{ "type": "VANILLA" }"#).unwrap();
+        assert_eq!(ModProfile::from_modinfo(&vanilla), None);
+    }
+}
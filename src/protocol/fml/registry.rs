@@ -0,0 +1,89 @@
+use crate::protocol::fml::ModIdData;
+use std::collections::HashMap;
+
+/// Префиксы имён в `ModIdData`: блоки и предметы лежат в одной таблице
+pub const BLOCK_PREFIX: char = '\u{1}';
+pub const ITEM_PREFIX: char = '\u{2}';
+
+/// Числовые id блоков и предметов конкретного сервера.
+/// На модовых сборках id раздаются при первом запуске мира и на каждом сервере свои,
+/// поэтому таблица берётся из рукопожатия FML, а не зашивается в код.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdRegistry {
+    blocks: HashMap<i32, String>,
+    items: HashMap<i32, String>,
+    block_ids: HashMap<String, i32>,
+    item_ids: HashMap<String, i32>,
+}
+
+impl IdRegistry {
+    pub fn from_mod_id_data(data: &ModIdData) -> Self {
+        let mut registry = Self::default();
+        for (name, id) in &data.ids {
+            if let Some(block) = name.strip_prefix(BLOCK_PREFIX) {
+                registry.blocks.insert(*id, block.to_string());
+                registry.block_ids.insert(block.to_string(), *id);
+            } else if let Some(item) = name.strip_prefix(ITEM_PREFIX) {
+                registry.items.insert(*id, item.to_string());
+                registry.item_ids.insert(item.to_string(), *id);
+            }
+        }
+        registry
+    }
+
+    /// Пустая таблица — vanilla-сервер или рукопожатие FML ещё не дошло до `ModIdData`
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.items.is_empty()
+    }
+
+    /// Имя блока вида `modid:name`
+    pub fn block_name(&self, id: i32) -> Option<&str> {
+        self.blocks.get(&id).map(String::as_str)
+    }
+
+    /// Имя предмета. У блоков-предметов (`ItemBlock`) тот же id, что у блока,
+    /// поэтому при отсутствии предмета берётся имя блока.
+    pub fn item_name(&self, id: i32) -> Option<&str> {
+        self.items
+            .get(&id)
+            .or_else(|| self.blocks.get(&id))
+            .map(String::as_str)
+    }
+
+    pub fn block_id(&self, name: &str) -> Option<i32> {
+        self.block_ids.get(name).copied()
+    }
+
+    pub fn item_id(&self, name: &str) -> Option<i32> {
+        self.item_ids
+            .get(name)
+            .or_else(|| self.block_ids.get(name))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_blocks_and_items() {
+        let data = ModIdData {
+            ids: vec![
+                ("\u{1}minecraft:stone".to_string(), 1),
+                ("\u{2}minecraft:stone".to_string(), 1),
+                ("\u{1}IC2:blockMachine".to_string(), 250),
+                ("\u{2}IC2:itemCable".to_string(), 4097),
+            ],
+            ..ModIdData::default()
+        };
+        let registry = IdRegistry::from_mod_id_data(&data);
+        assert_eq!(registry.block_name(250), Some("IC2:blockMachine"));
+        assert_eq!(registry.item_name(4097), Some("IC2:itemCable"));
+        assert_eq!(registry.block_name(4097), None);
+        // ItemBlock без отдельной записи предмета
+        assert_eq!(registry.item_name(250), Some("IC2:blockMachine"));
+        assert_eq!(registry.block_id("minecraft:stone"), Some(1));
+        assert_eq!(registry.item_id("IC2:itemCable"), Some(4097));
+    }
+}
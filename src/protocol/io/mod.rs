@@ -200,6 +200,49 @@ pub async fn write_bytearray_short<W: AsyncWrite + Unpin>(writer: &mut W, data:
     Ok(())
 }
 
+/// Наибольшее значение varshort: 15 бит в short и ещё 8 в дополнительном байте
+pub const MAX_VARSHORT: usize = 0x7FFFFF;
+
+/// Forge varshort (`ByteBufUtils.readVarShort`): u16, а при взведённом старшем бите
+/// за ним байт со старшими разрядами
+pub async fn read_varshort<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<usize> {
+    let low = read_u16_be(reader).await? as usize;
+    if low & 0x8000 == 0 {
+        return Ok(low);
+    }
+    let high = read_u8_be(reader).await? as usize;
+    Ok(high << 15 | low & 0x7FFF)
+}
+
+pub async fn write_varshort<W: AsyncWrite + Unpin>(writer: &mut W, value: usize) -> io::Result<()> {
+    if value > MAX_VARSHORT {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Value does not fit in a varshort"));
+    }
+    let high = value >> 15;
+    if high == 0 {
+        return write_u16_be(writer, value as u16).await;
+    }
+    write_u16_be(writer, (value & 0x7FFF | 0x8000) as u16).await?;
+    write_u8_be(writer, high as u8).await
+}
+
+/// Массив с varshort-длиной. Длине не верим: буфер растёт по мере чтения
+pub async fn read_bytearray_varshort<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_varshort(reader).await?;
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf).await?;
+    if buf.len() != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated byte array"));
+    }
+    Ok(buf)
+}
+
+pub async fn write_bytearray_varshort<W: AsyncWrite + Unpin>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    write_varshort(writer, data.len()).await?;
+    writer.write_all(data).await?;
+    Ok(())
+}
+
 pub async fn read_varstring<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<String> {
     let len = read_varint(reader).await?;
     if len < 0 {
@@ -217,4 +260,65 @@ pub async fn write_varstring<W: AsyncWrite + Unpin>(writer: &mut W, s: &str) ->
     write_varint(writer, s.len() as i32).await?;
     writer.write_all(s.as_bytes()).await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Строка `DataOutputStream.writeUTF`: u16-длина в байтах и modified UTF-8
+/// (`\0` — два байта, символы вне BMP — суррогатными парами по три байта)
+pub async fn read_java_utf<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<String> {
+    let len = read_u16_be(reader).await?;
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf).await?;
+
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid modified UTF-8 string");
+    let mut units = Vec::with_capacity(buf.len());
+    let mut bytes = buf.iter().copied();
+    while let Some(b) = bytes.next() {
+        let unit = match b {
+            0x01..=0x7F => b as u16,
+            0xC0..=0xDF => {
+                let b2 = bytes.next().ok_or_else(invalid)?;
+                ((b as u16 & 0x1F) << 6) | (b2 as u16 & 0x3F)
+            }
+            0xE0..=0xEF => {
+                let b2 = bytes.next().ok_or_else(invalid)?;
+                let b3 = bytes.next().ok_or_else(invalid)?;
+                ((b as u16 & 0x0F) << 12) | ((b2 as u16 & 0x3F) << 6) | (b3 as u16 & 0x3F)
+            }
+            _ => return Err(invalid()),
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).map_err(|_| invalid())
+}
+
+pub async fn write_java_utf<W: AsyncWrite + Unpin>(writer: &mut W, s: &str) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(s.len());
+    for unit in s.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => buf.push(unit as u8),
+            0x0000..=0x07FF => buf.extend_from_slice(&[0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8]),
+            _ => buf.extend_from_slice(&[
+                0xE0 | (unit >> 12) as u8,
+                0x80 | ((unit >> 6) & 0x3F) as u8,
+                0x80 | (unit & 0x3F) as u8,
+            ]),
+        }
+    }
+    let len = u16::try_from(buf.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "String is too long for writeUTF"))?;
+    write_u16_be(writer, len).await?;
+    writer.write_all(&buf).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn java_utf_encodes_null_and_astral_chars() {
+        let mut buf = Vec::new();
+        write_java_utf(&mut buf, "a\0😀").await.unwrap();
+        assert_eq!(buf, [0, 9, b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(read_java_utf(&mut &buf[..]).await.unwrap(), "a\0😀");
+    }
+}
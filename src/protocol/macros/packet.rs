@@ -26,7 +26,7 @@ macro_rules! server_packets {
         }
 
         impl $ty_name {
-            pub fn try_from(packet: Box<dyn $crate::protocol::packets::AsyncPacket>) -> Option<Self> {
+            pub fn try_from(packet: &dyn $crate::protocol::packets::AsyncPacket) -> Option<Self> {
                 let id = packet.get_id();
                 let state = packet.get_state();
                 match (id, state) {
@@ -76,11 +76,18 @@ macro_rules! server_packets {
                 fn get_state(&self) -> Option<$crate::connection::connection_state::ConnectionState> { Self::PACKET_STATE }
                 fn get_bound(&self) -> $crate::protocol::packets::Bound { Self::BOUND }
                 fn as_any(&self) -> &dyn std::any::Any { self }
-                async fn write_to_boxed(&self, _writer: &mut (dyn tokio::io::AsyncWrite + Unpin + Send)) -> std::io::Result<()> {
-                    if Self::BOUND != $crate::protocol::packets::Bound::Client {
-                        panic!("write_to_boxed() called on a server-bound packet: {:?}", Self::PACKET_ID);
-                    }
-                    Ok(())
+                async fn write_to_boxed(&self, writer: &mut (dyn tokio::io::AsyncWrite + Unpin + Send)) -> std::io::Result<()> {
+                    use tokio::io::AsyncWriteExt;
+                    let mut buf = Vec::new();
+                    $crate::protocol::io::write_varint(&mut buf, Self::PACKET_ID).await?;
+                    $(
+                        <$field_ty as $crate::protocol::fields::AsyncWriteField>::write_field(&self.$field_name, &mut buf).await?;
+                    )*
+                    let packet_length = buf.len() as i32;
+                    let mut full_packet = Vec::new();
+                    $crate::protocol::io::write_varint(&mut full_packet, packet_length).await?;
+                    full_packet.extend_from_slice(&buf);
+                    writer.write_all(&full_packet).await
                 }
             }
         )*
@@ -99,12 +106,14 @@ macro_rules! server_packets {
                 }
 
                 $(
+                    #[allow(clippy::too_many_arguments)]
                     pub fn [<$name:snake>]($($field_name: $field_ty),*) -> Self {
                         Self::$name($name { $($field_name),* })
                     }
                 )*
             }
 
+            #[allow(async_fn_in_trait)]
             pub trait [<$ty_name Handler>] {
                 $(
                     async fn [<handle_ $name:snake>](&mut self, _packet: $name) {
@@ -155,9 +164,6 @@ macro_rules! client_packets {
                 #[allow(unused_variables)]
                 pub async fn read_from<R>(reader: &mut R) -> std::io::Result<Self>
                 where R: tokio::io::AsyncRead + std::marker::Unpin + Send {
-                    if Self::BOUND != $crate::protocol::packets::Bound::Server {
-                        panic!("read_from() called on a client-bound packet: {:?}", Self::PACKET_ID);
-                    }
                     $(
                         let $field_name = <$field_ty as $crate::protocol::fields::AsyncReadField>::read_field(reader).await?;
                     )*
@@ -172,9 +178,6 @@ macro_rules! client_packets {
                 fn get_bound(&self) -> $crate::protocol::packets::Bound { Self::BOUND }
                 fn as_any(&self) -> &dyn std::any::Any { self }
                 async fn write_to_boxed(&self, writer: &mut (dyn tokio::io::AsyncWrite + Unpin + Send)) -> std::io::Result<()> {
-                    if Self::BOUND != $crate::protocol::packets::Bound::Client {
-                        panic!("write_to_boxed() called on a server-bound packet: {:?}", Self::PACKET_ID);
-                    }
                     use tokio::io::AsyncWriteExt;
                     let mut buf = Vec::new();
                     $crate::protocol::io::write_varint(&mut buf, Self::PACKET_ID).await?;
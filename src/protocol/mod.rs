@@ -1,5 +1,8 @@
+pub mod channels;
+pub mod chat;
 pub mod crypto;
 pub mod fields;
+pub mod fml;
 pub mod io;
 pub mod macros;
 pub mod packets;
@@ -30,6 +30,9 @@ client_packets! {
             pitch: Float,
             on_ground: Boolean
         },
+        TabComplete (0x14, Play) {
+            text: VarString
+        },
         ClientSettings (0x15, Play) {
             locale: VarString,
             view_distance: Byte,
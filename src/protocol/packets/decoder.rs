@@ -2,21 +2,23 @@ use crate::protocol::io::read_varint;
 use crate::protocol::packets::server::*;
 use std::io;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
-use crate::connection::conn_reader::ConnReader;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use crate::connection::connection_state::ConnectionState;
-use crate::protocol::packets::{AsyncPacket, Bound};
+use crate::protocol::packets::{client, AsyncPacket, Bound};
 
 static DECODED_PACKETS: AtomicUsize = AtomicUsize::new(0);
 
+/// Наибольший размер пакета, как у ванильного декодера (3 байта VarInt-длины)
+pub const MAX_FRAME_SIZE: usize = 0x200000;
+
 #[macro_export]
 macro_rules! try_decode_packet {
-    ($reader:expr, $packet_id:expr, { $( $id:expr => $Type:ident ),* $(,)? }) => {
+    ($reader:expr, $packet_id:expr, $state:expr, $bound:ident, { $( $id:expr => $Type:ty ),* $(,)? }) => {
         match $packet_id {
             $(
                 $id => {
                     // println!("Decoding packet with id 0x{:X}", $packet_id);
-                    let pkt = $Type::read_from($reader).await?;
+                    let pkt = <$Type>::read_from($reader).await?;
                     if $packet_id != 0x26 {
                         // println!("Got packet 0x{:X}({}): {:?}", $packet_id, $packet_id, pkt);
                     }
@@ -25,14 +27,14 @@ macro_rules! try_decode_packet {
                 }
             ),*,
             other => {
-                let mut remaining = vec![0u8; 1024];
-                let bytes_read = $reader.read(&mut remaining).await.unwrap_or(0);
-                remaining.truncate(bytes_read);
-                println!("DEBUG: Unknown packet. Packet ID: {:#X}, Remaining data: {:?}", other, &remaining[..]);
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Unknown packet id: 0x{:X}({}). Data: {:?}", other, other, remaining),
-                ));
+                let mut data = Vec::new();
+                $reader.read_to_end(&mut data).await?;
+                Box::new($crate::protocol::packets::UnknownPacket {
+                    id: other,
+                    state: $state,
+                    bound: $crate::bound_from_ident!($bound),
+                    data,
+                }) as Box<dyn $crate::protocol::packets::AsyncPacket + Send>
             }
         }
     }
@@ -46,23 +48,90 @@ pub async fn read_server_packet_by_state<R>(
 where
     R: AsyncRead + Unpin + Send,
 {
-    let packet_length = read_varint(reader).await?;
-    println!("Packet length {}", packet_length);
+    let frame = read_frame(reader).await?;
+    decode_server_packet(&frame, state).await
+}
+
+/// Читает один пакет целиком (без префикса длины), не разбирая его.
+/// Состояние для декодирования можно выбрать уже после того, как пакет пришёл.
+pub async fn read_frame<R>(reader: &mut R) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let packet_length = frame_length(read_varint(reader).await?)?;
+    let mut frame = vec![0u8; packet_length];
+    reader.read_exact(&mut frame).await?;
+    Ok(frame)
+}
 
-    let mut limited_reader = reader.take(packet_length as u64);
+/// Отрезает от начала буфера один полный пакет (без префикса длины).
+/// `None` — пакет ещё не пришёл целиком. В отличие от [`read_frame`], ничего не теряет
+/// при отмене, поэтому подходит для чтения под таймаутом.
+pub fn take_frame(buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+    let mut packet_length = 0i32;
+    let mut header_len = 0;
+    loop {
+        let Some(&byte) = buf.get(header_len) else {
+            return Ok(None);
+        };
+        packet_length |= ((byte & 0x7F) as i32) << (7 * header_len);
+        header_len += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if header_len >= 5 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "VarInt is too big"));
+        }
+    }
+    let end = header_len + frame_length(packet_length)?;
+    if buf.len() < end {
+        return Ok(None);
+    }
+    let frame = buf[header_len..end].to_vec();
+    buf.drain(..end);
+    Ok(Some(frame))
+}
+
+fn expect_bound(packet: Box<dyn AsyncPacket + Send>, bound: Bound) -> io::Result<Box<dyn AsyncPacket + Send>> {
+    if packet.get_bound() != bound {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Packet 0x{:02X} is not {:?}-bound", packet.get_id(), bound),
+        ));
+    }
+    Ok(packet)
+}
+
+/// Длина из заголовка пакета. Проверяется до выделения буфера: её присылает другая сторона.
+fn frame_length(packet_length: i32) -> io::Result<usize> {
+    if packet_length < 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Negative packet length"));
+    }
+    if packet_length as usize > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Packet length {} exceeds {}", packet_length, MAX_FRAME_SIZE),
+        ));
+    }
+    Ok(packet_length as usize)
+}
+
+pub async fn decode_server_packet(
+    frame: &[u8],
+    state: ConnectionState,
+) -> io::Result<Box<dyn AsyncPacket + Send>> {
+    let mut limited_reader = frame;
 
     let packet_id = read_varint(&mut limited_reader).await?;
-    println!("Packet id 0x{:X}", packet_id);
-    
 
     let packet = match state {
-        ConnectionState::Login => Ok(try_decode_packet!(&mut limited_reader, packet_id, {
+        ConnectionState::Login => Ok(try_decode_packet!(&mut limited_reader, packet_id, state, S, {
             0x00 => LoginDisconnect,
             0x01 => EncryptionRequest,
             0x02 => LoginSuccess
         })),
         ConnectionState::Play => {
-            let packet = try_decode_packet!(&mut limited_reader, packet_id, {
+            let packet = try_decode_packet!(&mut limited_reader, packet_id, state, S, {
                 0x00 => KeepAlive,
                 0x01 => JoinGame,
                 0x02 => SChatMessage,
@@ -85,18 +154,19 @@ where
                 0x13 => DestroyEntities,
                 0x14 => Entity,
                 0x15 => EntityRelMove,
-                0x16 => EntityLookAndMovement,
+                0x16 => EntityLook,
                 0x17 => EntityLookMove,
                 0x18 => EntityTeleport,
-                0x19 => EntityStatus,
-                0x1A => AttachEntity,
-                0x1B => EntityMetadata,
-                0x1C => EntityEffect,
-                0x1D => RemoveEntityEffect,
-                0x1E => Experience,
+                0x19 => EntityHeadLook,
+                0x1A => EntityStatus,
+                0x1B => AttachEntity,
+                0x1C => EntityMetadata,
+                0x1D => EntityEffect,
+                0x1E => RemoveEntityEffect,
                 0x37 => Statistics,
                 0x38 => PlayerListItem,
                 0x39 => PlayerAbilities,
+                0x3A => STabComplete,
                 0x3F => CustomPayload,
                 0x2B => ChangeGameState,
                 0x30 => WindowItems,
@@ -112,23 +182,106 @@ where
                 0x23 => BlockChange,
                 0x28 => Effect,
                 0x22 => MultiBlockChange,
+                0x40 => Disconnect,
             });
 
-            assert_eq!(packet.get_bound(), Bound::Server);
-            Ok(packet)
+            expect_bound(packet, Bound::Server)
         }
-        _ => Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Unsupported state for decoding: {:?}", state),
-        )),
+        _ => Err(io::Error::other(format!(
+            "Unsupported state for decoding: {:?}",
+            state
+        ))),
     };
 
-    let remaining = limited_reader.limit();
-    if remaining > 0 {
-        let mut discard = vec![0u8; remaining as usize];
-        limited_reader.read_exact(&mut discard).await?;
-    }
-
     packet
 }
 
+/// Разбирает пакет, отправленный клиентом, — для mock-серверов
+pub async fn decode_client_packet(
+    frame: &[u8],
+    state: ConnectionState,
+) -> io::Result<Box<dyn AsyncPacket + Send>> {
+    let mut limited_reader = frame;
+
+    let packet_id = read_varint(&mut limited_reader).await?;
+
+    match state {
+        ConnectionState::Handshaking => Ok(try_decode_packet!(&mut limited_reader, packet_id, state, C, {
+            0x00 => client::Handshake
+        })),
+        ConnectionState::Login => Ok(try_decode_packet!(&mut limited_reader, packet_id, state, C, {
+            0x00 => client::LoginStart,
+            0x01 => client::EncryptionResponse
+        })),
+        ConnectionState::Play => {
+            let packet = try_decode_packet!(&mut limited_reader, packet_id, state, C, {
+                0x00 => client::KeepAlive,
+                0x01 => client::ChatMessage,
+                0x06 => client::PlayerPosLook,
+                0x14 => client::TabComplete,
+                0x15 => client::ClientSettings,
+                0x16 => client::ClientStatus,
+                0x17 => client::CustomPayload,
+            });
+
+            expect_bound(packet, Bound::Client)
+        }
+        _ => Err(io::Error::other(format!(
+            "Unsupported state for decoding: {:?}",
+            state
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::io::write_varint;
+    use crate::protocol::fields::{ByteArrayVarShort, VarString};
+    use crate::protocol::packets::{server, AsyncPacketExt, UnknownPacket};
+
+    #[tokio::test]
+    async fn oversized_frames_are_rejected_before_allocation() {
+        let mut header = Vec::new();
+        write_varint(&mut header, i32::MAX).await.unwrap();
+        assert_eq!(
+            read_frame(&mut &header[..]).await.unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(take_frame(&mut header.clone()).is_err());
+
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 3).await.unwrap();
+        buf.extend_from_slice(&[0x00, 1, 2, 0xFF]);
+        assert_eq!(take_frame(&mut buf).unwrap(), Some(vec![0x00, 1, 2]));
+        assert_eq!(buf, [0xFF]);
+    }
+
+    #[tokio::test]
+    async fn forge_payloads_use_varshort_length() {
+        let payload = server::CustomPayload {
+            channel: VarString("FML|HS".to_string()),
+            data: ByteArrayVarShort((0..40_000).map(|i| i as u8).collect()),
+        };
+        let mut buf = Vec::new();
+        payload.write_to_boxed(&mut buf).await.unwrap();
+        let frame = take_frame(&mut buf).unwrap().unwrap();
+        // id, канал, затем 40000 = 0x9C40: short с взведённым старшим битом и байт 1
+        assert_eq!(frame[8..11], [0x9C, 0x40, 0x01]);
+
+        let packet = decode_server_packet(&frame, ConnectionState::Play).await.unwrap();
+        let decoded = packet.as_packet::<server::CustomPayload>().unwrap();
+        assert_eq!(decoded.data, payload.data);
+    }
+
+    #[tokio::test]
+    async fn unmodelled_packets_decode_as_unknown() {
+        // 0x3E Teams: имя команды и режим
+        let frame = [0x3E, 0x04, b't', b'e', b'a', b'm', 0x01];
+        let packet = decode_server_packet(&frame, ConnectionState::Play).await.unwrap();
+        let unknown = packet.as_any().downcast_ref::<UnknownPacket>().unwrap();
+        assert_eq!(unknown.id, 0x3E);
+        assert_eq!(unknown.data, &frame[1..]);
+        assert!(ServerPacket::try_from(&*packet).is_none());
+    }
+}
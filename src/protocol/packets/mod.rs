@@ -27,6 +27,42 @@ pub trait AsyncPacket: Any + Send + Sync {
     async fn write_to_boxed(&self, writer: &mut (dyn AsyncWrite + Unpin + Send)) -> io::Result<()>;
 }
 
+/// Пакет, для которого нет модели: id и сырые данные после него.
+/// Декодер возвращает его вместо ошибки, чтобы соединение пропускало такие пакеты.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownPacket {
+    pub id: i32,
+    pub state: ConnectionState,
+    pub bound: Bound,
+    pub data: Vec<u8>,
+}
+
+#[async_trait::async_trait]
+impl AsyncPacket for UnknownPacket {
+    fn get_id(&self) -> i32 {
+        self.id
+    }
+    fn get_state(&self) -> Option<ConnectionState> {
+        Some(self.state)
+    }
+    fn get_bound(&self) -> Bound {
+        self.bound
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    async fn write_to_boxed(&self, writer: &mut (dyn AsyncWrite + Unpin + Send)) -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+        let mut buf = Vec::new();
+        crate::protocol::io::write_varint(&mut buf, self.id).await?;
+        buf.extend_from_slice(&self.data);
+        let mut full_packet = Vec::new();
+        crate::protocol::io::write_varint(&mut full_packet, buf.len() as i32).await?;
+        full_packet.extend_from_slice(&buf);
+        writer.write_all(&full_packet).await
+    }
+}
+
 pub trait AsyncPacketExt {
     fn as_packet<T: 'static>(&self) -> Option<&T>;
 }
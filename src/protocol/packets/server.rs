@@ -1,5 +1,6 @@
 use crate::protocol::fields::*;
 use crate::server_packets;
+use crate::protocol::fml::IdRegistry;
 
 server_packets! {
     pub enum ServerPacket {
@@ -34,9 +35,9 @@ server_packets! {
             time_of_day: Long
         },
         EntityEquipment (0x04, Play) {
-            entity_id: VarInt,
+            entity_id: Int,
             slot: Short,
-            item: Int
+            item: ItemStack
         },
         SpawnPosition (0x05, Play) {
             x: Int,
@@ -78,12 +79,13 @@ server_packets! {
         SpawnPlayer (0x0C, Play) {
             entity_id: VarInt,
             profile: GameProfile,
-            x: Double,
-            y: Double,
-            z: Double,
-            yaw: Float,
-            pitch: Float,
-            current_item: Byte
+            x: Int,
+            y: Int,
+            z: Int,
+            yaw: Byte,
+            pitch: Byte,
+            current_item: Short,
+            metadata: Metadata
         },
         CollectItem (0x0D, Play) {
             collector_entity_id: VarInt,
@@ -98,20 +100,21 @@ server_packets! {
             z: Int,
             pitch: Byte,
             yaw: Byte,
-            extra: Int
+            data: ObjectData
         },
         SpawnMob (0x0F, Play) {
             entity_id: VarInt,
             mob_type: Byte,
-            x: Double,
-            y: Double,
-            z: Double,
-            yaw: Float,
-            pitch: Float,
-            head_yaw: Float,
+            x: Int,
+            y: Int,
+            z: Int,
+            yaw: Byte,
+            pitch: Byte,
+            head_yaw: Byte,
             velocity_x: Short,
             velocity_y: Short,
-            velocity_z: Short
+            velocity_z: Short,
+            metadata: Metadata
         },
         SpawnPainting (0x10, Play) {
             entity_id: VarInt,
@@ -119,7 +122,7 @@ server_packets! {
             x: Int,
             y: Int,
             z: Int,
-            direction: Byte
+            direction: Int
         },
         SpawnExperienceOrb (0x11, Play) {
             entity_id: VarInt,
@@ -129,13 +132,13 @@ server_packets! {
             count: Short
         },
         EntityVelocity (0x12, Play) {
-            entity_id: VarInt,
+            entity_id: Int,
             velocity_x: Short,
             velocity_y: Short,
             velocity_z: Short
         },
         DestroyEntities (0x13, Play) {
-            unimpl: Unimplemented
+            entity_ids: EntityIds
         },
         Entity (0x14, Play) {
             entity_id: Int,
@@ -146,7 +149,7 @@ server_packets! {
             y: Byte,
             z: Byte
         },
-        EntityLookAndMovement (0x16, Play) {
+        EntityLook (0x16, Play) {
             entity_id: Int,
             yaw: Byte,
             pitch: Byte
@@ -167,33 +170,33 @@ server_packets! {
             yaw: Byte,
             pitch: Byte,
         },
-        EntityStatus (0x19, Play) {
-            entity_id: VarInt,
+        EntityHeadLook (0x19, Play) {
+            entity_id: Int,
+            head_yaw: Byte
+        },
+        EntityStatus (0x1A, Play) {
+            entity_id: Int,
             status: Byte
         },
-        AttachEntity (0x1A, Play) {
-            entity_id: VarInt,
-            vehicle_id: Int
+        AttachEntity (0x1B, Play) {
+            entity_id: Int,
+            vehicle_id: Int,
+            leash: Boolean
         },
-        EntityMetadata (0x1B, Play) {
-            entity_id: VarInt,
-            metadata: ByteArrayVarInt
+        EntityMetadata (0x1C, Play) {
+            entity_id: Int,
+            metadata: Metadata
         },
-        EntityEffect (0x1C, Play) {
-            entity_id: VarInt,
+        EntityEffect (0x1D, Play) {
+            entity_id: Int,
             effect_id: Byte,
             amplifier: Byte,
-            duration: VarInt
+            duration: Short
         },
-        RemoveEntityEffect (0x1D, Play) {
-            entity_id: VarInt,
+        RemoveEntityEffect (0x1E, Play) {
+            entity_id: Int,
             effect_id: Byte
         },
-        Experience (0x1E, Play) {
-            experience_bar: Float,
-            total_experience: VarInt,
-            level: VarInt
-        },
         SetExperience (0x1F, Play) {
             experience_bar: Float,
             level: Short,
@@ -204,10 +207,17 @@ server_packets! {
             properties: Vec<EntityProperty>
         },
         ChunkData (0x21, Play) {
-            unimpl: Unimplemented
+            x: Int,
+            z: Int,
+            ground_up: Boolean,
+            primary_bitmap: UShort,
+            add_bitmap: UShort,
+            data: ByteArrayInt
         },
         MultiBlockChange (0x22, Play) {
-            unimpl: Unimplemented
+            chunk_x: Int,
+            chunk_z: Int,
+            records: BlockRecords
         },
         BlockChange (0x23, Play) {
             x: Int,
@@ -217,10 +227,17 @@ server_packets! {
             meta: Byte
         },
         MapChunkBulk (0x26, Play) {
-            unimpl: Unimplemented
+            bulk: ChunkBulk
         },
         Explosion (0x27, Play) {
-            unimpl: Unimplemented
+            x: Float,
+            y: Float,
+            z: Float,
+            radius: Float,
+            records: ExplosionRecords,
+            knockback_x: Float,
+            knockback_y: Float,
+            knockback_z: Float
         },
         Effect (0x28, Play) {
             effect_id: Int,
@@ -271,9 +288,63 @@ server_packets! {
             fly_speed: Float,
             walk_speed: Float
         },
+        STabComplete (0x3A, Play) {
+            matches: VecVarInt<VarString>
+        },
         CustomPayload (0x3F, Play) {
             channel: VarString,
-            data: ByteArrayVarInt
+            data: ByteArrayVarShort
+        },
+        Disconnect (0x40, Play) {
+            reason: VarString
         }
     }
 }
+
+impl BlockChange {
+    /// Имя нового блока по таблице id сервера (см. `Connection::registry`)
+    pub fn block_name<'a>(&self, registry: &'a IdRegistry) -> Option<&'a str> {
+        registry.block_name(self.block_id.0)
+    }
+}
+
+impl MultiBlockChange {
+    /// Мировые координаты записи
+    pub fn position(&self, record: &BlockRecord) -> (i32, i32, i32) {
+        (
+            self.chunk_x.0 * 16 + record.x as i32,
+            record.y as i32,
+            self.chunk_z.0 * 16 + record.z as i32,
+        )
+    }
+}
+
+impl Explosion {
+    /// Мировые координаты разрушенных блоков. Клиент отсчитывает смещения от центра,
+    /// приведённого к int отбрасыванием дробной части.
+    pub fn affected_blocks(&self) -> impl Iterator<Item = (i32, i32, i32)> + '_ {
+        let (x, y, z) = (self.x.0 as i32, self.y.0 as i32, self.z.0 as i32);
+        self.records
+            .0
+            .iter()
+            .map(move |r| (x + r.dx as i32, y + r.dy as i32, z + r.dz as i32))
+    }
+
+    /// Скорость, которую взрыв добавляет игроку
+    pub fn knockback(&self) -> (f32, f32, f32) {
+        (self.knockback_x.0, self.knockback_y.0, self.knockback_z.0)
+    }
+}
+
+impl ChunkData {
+    /// Столбец без секций с `ground_up` — сервер выгружает чанк
+    pub fn is_unload(&self) -> bool {
+        self.ground_up.0 && self.primary_bitmap.0 == 0
+    }
+}
+
+impl STabComplete {
+    pub fn into_matches(self) -> Vec<String> {
+        self.matches.0.into_iter().map(|m| m.0).collect()
+    }
+}
@@ -6,7 +6,11 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time::Instant;
 
-use crate::protocol::io::{read_varint, read_varstring, write_varint, write_varstring};
+use crate::connection::handshake::{split_address, HandshakeOptions};
+use crate::protocol::chat::{self, Language};
+use crate::protocol::fml::ModInfo;
+use crate::protocol::io::{read_varint, read_varstring, write_varint};
+use crate::protocol::packets::AsyncPacket;
 
 #[derive(Debug)]
 pub struct PingResponse {
@@ -16,6 +20,8 @@ pub struct PingResponse {
     pub online_players: u32,
     pub max_players: u32,
     pub latency_ms: u128,
+    /// Есть только у Forge-серверов, см. `ModProfile::from_modinfo`
+    pub modinfo: Option<ModInfo>,
 }
 
 #[derive(Deserialize)]
@@ -23,6 +29,8 @@ struct StatusResponse {
     version: VersionInfo,
     players: PlayersInfo,
     description: serde_json::Value,
+    #[serde(default)]
+    modinfo: Option<ModInfo>,
 }
 
 #[derive(Deserialize)]
@@ -40,33 +48,12 @@ struct PlayersInfo {
 pub async fn ping_status(addr: &str) -> io::Result<PingResponse> {
     let mut stream = TcpStream::connect(addr).await?;
 
-    let (host, port) = if let Some(pos) = addr.rfind(':') {
-        (
-            &addr[..pos],
-            addr[pos + 1..].parse::<u16>().unwrap_or(25565),
-        )
-    } else {
-        (addr, 25565)
-    };
-    let protocol_version = 5;
-    let mut handshake_data = Vec::new();
-    handshake_data.push(0x00);
-    let mut buf = Vec::new();
-    write_varint(&mut buf, protocol_version).await?;
-    handshake_data.extend(buf);
-    let mut buf = Vec::new();
-    write_varstring(&mut buf, host).await?;
-    handshake_data.extend(buf);
-    handshake_data.extend(&port.to_be_bytes());
-    let mut buf = Vec::new();
-    write_varint(&mut buf, 1).await?;
-    handshake_data.extend(buf);
-
+    let (host, port) = split_address(addr);
     let mut packet = Vec::new();
-    let mut buf = Vec::new();
-    write_varint(&mut buf, handshake_data.len() as i32).await?;
-    packet.extend(buf);
-    packet.extend(handshake_data);
+    HandshakeOptions::status()
+        .build(&host, port)
+        .write_to_boxed(&mut packet)
+        .await?;
     stream.write_all(&packet).await?;
 
     let mut packet = Vec::new();
@@ -112,13 +99,7 @@ pub async fn ping_status(addr: &str) -> io::Result<PingResponse> {
 
     let status: StatusResponse = serde_json::from_str(&json_response)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid JSON: {}", e)))?;
-    let motd = if status.description.is_string() {
-        status.description.as_str().unwrap_or("").to_string()
-    } else if let Some(text) = status.description.get("text") {
-        text.as_str().unwrap_or("").to_string()
-    } else {
-        status.description.to_string()
-    };
+    let motd = chat::render(&status.description, Language::default());
 
     Ok(PingResponse {
         motd,
@@ -127,5 +108,6 @@ pub async fn ping_status(addr: &str) -> io::Result<PingResponse> {
         online_players: status.players.online,
         max_players: status.players.max,
         latency_ms: latency,
+        modinfo: status.modinfo,
     })
 }
@@ -62,10 +62,10 @@ pub async fn full_query(addr: &str) -> io::Result<QueryResponse> {
     loop {
         let mut buf = vec![0u8; 4096];
         match timeout(Duration::from_secs(5), socket.recv_from(&mut buf)).await {
-            Ok(Ok((n, src))) if n > 0 => {
+            Ok(Ok((n, _src))) if n > 0 => {
                 full_response.extend_from_slice(&buf[..n]);
             }
-            Ok(Ok((n, src))) => {
+            Ok(Ok((_n, src))) => {
                 println!("Received 0 bytes from {}", src);
                 break;
             }
@@ -0,0 +1,257 @@
+use crate::protocol::fml::IdRegistry;
+use tokio::io;
+
+pub const SECTION_COUNT: usize = 16;
+pub const WORLD_HEIGHT: i32 = 256;
+const SECTION_BLOCKS: usize = 4096;
+const NIBBLE_ARRAY: usize = SECTION_BLOCKS / 2;
+const BIOME_ARRAY: usize = 256;
+
+/// Блок: 12-битный id (младший байт и add-полубайт) и метаданные
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Block {
+    pub id: u16,
+    pub meta: u8,
+}
+
+impl Block {
+    pub const AIR: Block = Block { id: 0, meta: 0 };
+
+    pub fn new(id: u16, meta: u8) -> Self {
+        Self { id, meta }
+    }
+
+    pub fn is_air(&self) -> bool {
+        self.id == 0
+    }
+
+    /// Имя блока по таблице id сервера (см. `Connection::registry`)
+    pub fn name<'a>(&self, registry: &'a IdRegistry) -> Option<&'a str> {
+        registry.block_name(self.id as i32)
+    }
+}
+
+/// Секция 16×16×16. Индекс в массивах — `y << 8 | z << 4 | x`,
+/// полубайтовые массивы хранят чётный индекс в младших битах.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkSection {
+    blocks: Vec<u8>,
+    add: Option<Vec<u8>>,
+    meta: Vec<u8>,
+    block_light: Vec<u8>,
+    sky_light: Option<Vec<u8>>,
+}
+
+impl ChunkSection {
+    pub fn empty(has_sky: bool) -> Self {
+        Self {
+            blocks: vec![0; SECTION_BLOCKS],
+            add: None,
+            meta: vec![0; NIBBLE_ARRAY],
+            block_light: vec![0; NIBBLE_ARRAY],
+            // Пустая секция под открытым небом освещена полностью
+            sky_light: has_sky.then(|| vec![0xFF; NIBBLE_ARRAY]),
+        }
+    }
+
+    /// Координаты локальные, 0..16
+    pub fn block(&self, x: usize, y: usize, z: usize) -> Block {
+        let i = index(x, y, z);
+        let high = self.add.as_ref().map_or(0, |add| nibble(add, i));
+        Block {
+            id: (high as u16) << 8 | self.blocks[i] as u16,
+            meta: nibble(&self.meta, i),
+        }
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: Block) {
+        let i = index(x, y, z);
+        self.blocks[i] = block.id as u8;
+        let high = (block.id >> 8) as u8 & 0x0F;
+        if high != 0 || self.add.is_some() {
+            set_nibble(self.add.get_or_insert_with(|| vec![0; NIBBLE_ARRAY]), i, high);
+        }
+        set_nibble(&mut self.meta, i, block.meta);
+    }
+
+    pub fn block_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        nibble(&self.block_light, index(x, y, z))
+    }
+
+    /// `None` в измерениях без неба
+    pub fn sky_light(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+        self.sky_light.as_ref().map(|sky| nibble(sky, index(x, y, z)))
+    }
+}
+
+fn index(x: usize, y: usize, z: usize) -> usize {
+    y << 8 | z << 4 | x
+}
+
+fn nibble(array: &[u8], i: usize) -> u8 {
+    let byte = array[i >> 1];
+    if i & 1 == 0 {
+        byte & 0x0F
+    } else {
+        byte >> 4
+    }
+}
+
+fn set_nibble(array: &mut [u8], i: usize, value: u8) {
+    let byte = &mut array[i >> 1];
+    if i & 1 == 0 {
+        *byte = (*byte & 0xF0) | (value & 0x0F);
+    } else {
+        *byte = (*byte & 0x0F) | (value << 4);
+    }
+}
+
+/// Столбец чанка: 16 секций снизу вверх (`None` — секция пустая) и биомы
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkColumn {
+    pub x: i32,
+    pub z: i32,
+    has_sky: bool,
+    sections: Vec<Option<ChunkSection>>,
+    biomes: Option<Vec<u8>>,
+}
+
+impl ChunkColumn {
+    pub fn new(x: i32, z: i32, has_sky: bool) -> Self {
+        Self {
+            x,
+            z,
+            has_sky,
+            sections: vec![None; SECTION_COUNT],
+            biomes: None,
+        }
+    }
+
+    pub fn has_sky(&self) -> bool {
+        self.has_sky
+    }
+
+    pub fn section(&self, index: usize) -> Option<&ChunkSection> {
+        self.sections.get(index)?.as_ref()
+    }
+
+    /// Координаты внутри столбца: x и z 0..16, y 0..256
+    pub fn block(&self, x: usize, y: usize, z: usize) -> Block {
+        self.section(y >> 4)
+            .map_or(Block::AIR, |section| section.block(x, y & 15, z))
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: Block) {
+        let has_sky = self.has_sky;
+        let section = &mut self.sections[y >> 4];
+        if section.is_none() && block.is_air() {
+            return;
+        }
+        section
+            .get_or_insert_with(|| ChunkSection::empty(has_sky))
+            .set_block(x, y & 15, z, block);
+    }
+
+    /// Свет от блоков и от неба. В пустой секции свет от блоков нулевой, небо — полное.
+    pub fn light(&self, x: usize, y: usize, z: usize) -> (u8, Option<u8>) {
+        match self.section(y >> 4) {
+            Some(section) => (section.block_light(x, y & 15, z), section.sky_light(x, y & 15, z)),
+            None => (0, self.has_sky.then_some(15)),
+        }
+    }
+
+    pub fn biome(&self, x: usize, z: usize) -> Option<u8> {
+        self.biomes.as_ref().map(|biomes| biomes[z << 4 | x])
+    }
+
+    /// Разбирает несжатые данные столбца в формате 1.7.10 (`Chunk.fillChunk`): сначала
+    /// id блоков всех секций из `primary`, затем метаданные, свет от блоков, свет неба,
+    /// старшие полубайты id для секций из `add` и биомы, если `ground_up`.
+    /// Без `ground_up` меняются только пришедшие секции. Возвращает число прочитанных байт.
+    pub fn read_data(&mut self, data: &[u8], primary: u16, add: u16, ground_up: bool) -> io::Result<usize> {
+        let mut reader = DataReader { data, offset: 0 };
+        let has_sky = self.has_sky;
+        let present = |i: usize| primary & (1 << i) != 0;
+
+        for i in 0..SECTION_COUNT {
+            if present(i) {
+                let section = self.sections[i].get_or_insert_with(|| ChunkSection::empty(has_sky));
+                section.blocks.copy_from_slice(reader.take(SECTION_BLOCKS)?);
+            } else if ground_up {
+                self.sections[i] = None;
+            }
+        }
+        for i in (0..SECTION_COUNT).filter(|&i| present(i)) {
+            let bytes = reader.take(NIBBLE_ARRAY)?;
+            self.sections[i].as_mut().unwrap().meta.copy_from_slice(bytes);
+        }
+        for i in (0..SECTION_COUNT).filter(|&i| present(i)) {
+            let bytes = reader.take(NIBBLE_ARRAY)?;
+            self.sections[i].as_mut().unwrap().block_light.copy_from_slice(bytes);
+        }
+        if has_sky {
+            for i in (0..SECTION_COUNT).filter(|&i| present(i)) {
+                let bytes = reader.take(NIBBLE_ARRAY)?.to_vec();
+                self.sections[i].as_mut().unwrap().sky_light = Some(bytes);
+            }
+        }
+        for i in 0..SECTION_COUNT {
+            if add & (1 << i) != 0 {
+                // Старшие полубайты для секции, которой нет, клиент тоже пропускает
+                let bytes = reader.take(NIBBLE_ARRAY)?.to_vec();
+                if let Some(section) = self.sections[i].as_mut() {
+                    section.add = Some(bytes);
+                }
+            } else if ground_up {
+                if let Some(section) = self.sections[i].as_mut() {
+                    section.add = None;
+                }
+            }
+        }
+        if ground_up {
+            self.biomes = Some(reader.take(BIOME_ARRAY)?.to_vec());
+        }
+        Ok(reader.offset)
+    }
+}
+
+/// Размер несжатых данных столбца без света неба и с ним.
+/// По нему `ChunkData` определяет, есть ли в измерении небо.
+pub fn data_size(primary: u16, add: u16, ground_up: bool) -> (usize, usize) {
+    let sections = primary.count_ones() as usize;
+    let base = sections * (SECTION_BLOCKS + 2 * NIBBLE_ARRAY)
+        + add.count_ones() as usize * NIBBLE_ARRAY
+        + if ground_up { BIOME_ARRAY } else { 0 };
+    (base, base + sections * NIBBLE_ARRAY)
+}
+
+struct DataReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DataReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Chunk data is truncated"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_nibble_extends_block_id() {
+        let mut section = ChunkSection::empty(true);
+        section.set_block(3, 4, 5, Block::new(0x1A5, 7));
+        section.set_block(4, 4, 5, Block::new(1, 2));
+        assert_eq!(section.block(3, 4, 5), Block::new(0x1A5, 7));
+        assert_eq!(section.block(4, 4, 5), Block::new(1, 2));
+        assert_eq!(section.block(0, 0, 0), Block::AIR);
+    }
+}
@@ -0,0 +1,519 @@
+//! Сущности вокруг бота, собранные из пакетов появления, движения и удаления.
+//! Ведутся ядром соединения, см. `ConnectionHandle::entities`.
+
+use crate::protocol::fields::{ItemStack, Metadata, MetadataValue};
+use crate::protocol::packets::server::{
+    AttachEntity, DestroyEntities, EntityEquipment, EntityHeadLook, EntityLook, EntityLookMove, EntityMetadata,
+    EntityRelMove, EntityTeleport, EntityVelocity, ServerPacket, SpawnExperienceOrb, SpawnMob, SpawnObject,
+    SpawnPainting, SpawnPlayer,
+};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// Индекс метаданных со здоровьем у живых сущностей
+const HEALTH_INDEX: u8 = 6;
+
+/// Координаты в пакетах — fixed-point с 5 битами дробной части
+pub fn from_fixed(value: i32) -> f64 {
+    value as f64 / 32.0
+}
+
+/// Угол в пакетах — байт, 256 делений на оборот
+pub fn from_angle(value: u8) -> f32 {
+    value as i8 as f32 * 360.0 / 256.0
+}
+
+/// Скорость в пакетах — 1/8000 блока за тик
+pub fn from_velocity(value: i16) -> f64 {
+    value as f64 / 8000.0
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityKind {
+    Player { uuid: Uuid, name: String },
+    Mob { mob_type: u8 },
+    /// Объекты: вагонетки, стрелы, падающие блоки, предметы на земле
+    Object { object_type: u8, data: i32 },
+    /// Картина висит на блоке: координаты — блок, а не центр
+    Painting { title: String, direction: i32 },
+    ExperienceOrb { count: i16 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub id: i32,
+    pub kind: EntityKind,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Градусы
+    pub yaw: f32,
+    pub pitch: f32,
+    pub head_yaw: f32,
+    /// Блоки за тик
+    pub velocity: (f64, f64, f64),
+    /// Значения метаданных по индексу, последние присланные
+    pub metadata: BTreeMap<u8, MetadataValue>,
+    /// Слот 0 — предмет в руке, 1..=4 — броня от ботинок к шлему
+    pub equipment: BTreeMap<i16, ItemStack>,
+    /// Сущность, на которой эта едет
+    pub vehicle: Option<i32>,
+    /// Сущность, держащая эту на поводке
+    pub leash_holder: Option<i32>,
+}
+
+impl Entity {
+    pub fn new(id: i32, kind: EntityKind, x: f64, y: f64, z: f64) -> Self {
+        Self {
+            id,
+            kind,
+            x,
+            y,
+            z,
+            yaw: 0.0,
+            pitch: 0.0,
+            head_yaw: 0.0,
+            velocity: (0.0, 0.0, 0.0),
+            metadata: BTreeMap::new(),
+            equipment: BTreeMap::new(),
+            vehicle: None,
+            leash_holder: None,
+        }
+    }
+
+    pub fn is_player(&self) -> bool {
+        matches!(self.kind, EntityKind::Player { .. })
+    }
+
+    /// Ник, если это игрок
+    pub fn player_name(&self) -> Option<&str> {
+        match &self.kind {
+            EntityKind::Player { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Здоровье живой сущности из метаданных
+    pub fn health(&self) -> Option<f32> {
+        match (&self.kind, self.metadata.get(&HEALTH_INDEX)?) {
+            (EntityKind::Player { .. } | EntityKind::Mob { .. }, MetadataValue::Float(health)) => Some(*health),
+            _ => None,
+        }
+    }
+
+    pub fn distance_to(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.distance_squared(x, y, z).sqrt()
+    }
+
+    fn distance_squared(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (dx, dy, dz) = (self.x - x, self.y - y, self.z - z);
+        dx * dx + dy * dy + dz * dz
+    }
+
+    fn update_metadata(&mut self, metadata: &Metadata) {
+        for (index, value) in &metadata.0 {
+            self.metadata.insert(*index, value.clone());
+        }
+    }
+}
+
+/// Что поменялось в таблице сущностей. Публикуется как `EventKind::Entity`.
+/// Движения отдельными событиями не публикуются, их видно по пакетам.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityChange {
+    Spawned { id: i32 },
+    Removed { id: i32 },
+    /// Новый вход или респаун: все сущности выброшены
+    Cleared,
+}
+
+#[derive(Debug, Default)]
+pub struct EntityTracker {
+    entities: HashMap<i32, Entity>,
+}
+
+impl EntityTracker {
+    pub fn get(&self, id: i32) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &Entity> {
+        self.iter().filter(|entity| entity.is_player())
+    }
+
+    pub fn player_by_name(&self, name: &str) -> Option<&Entity> {
+        self.players().find(|entity| entity.player_name() == Some(name))
+    }
+
+    /// Ближайший к точке игрок. Себя сервер боту не присылает, так что его здесь нет.
+    pub fn nearest_player(&self, x: f64, y: f64, z: f64) -> Option<&Entity> {
+        self.nearest(x, y, z, Entity::is_player)
+    }
+
+    pub fn nearest<F>(&self, x: f64, y: f64, z: f64, mut filter: F) -> Option<&Entity>
+    where
+        F: FnMut(&Entity) -> bool,
+    {
+        self.iter()
+            .filter(|entity| filter(entity))
+            .min_by(|a, b| a.distance_squared(x, y, z).total_cmp(&b.distance_squared(x, y, z)))
+    }
+
+    /// Сущности не дальше `radius` от точки, в произвольном порядке
+    pub fn in_radius(&self, x: f64, y: f64, z: f64, radius: f64) -> impl Iterator<Item = &Entity> {
+        self.iter()
+            .filter(move |entity| entity.distance_squared(x, y, z) <= radius * radius)
+    }
+
+    pub fn insert(&mut self, entity: Entity) -> EntityChange {
+        let id = entity.id;
+        self.entities.insert(id, entity);
+        EntityChange::Spawned { id }
+    }
+
+    pub fn remove(&mut self, id: i32) -> Option<EntityChange> {
+        self.entities.remove(&id)?;
+        for entity in self.entities.values_mut() {
+            if entity.vehicle == Some(id) {
+                entity.vehicle = None;
+            }
+            if entity.leash_holder == Some(id) {
+                entity.leash_holder = None;
+            }
+        }
+        Some(EntityChange::Removed { id })
+    }
+
+    pub fn clear(&mut self) -> Option<EntityChange> {
+        if self.entities.is_empty() {
+            return None;
+        }
+        self.entities.clear();
+        Some(EntityChange::Cleared)
+    }
+
+    /// Применяет пакет, если он касается сущностей. Пакеты про неизвестные id
+    /// пропускаются, как и в клиенте.
+    pub fn apply(&mut self, packet: &ServerPacket) -> Vec<EntityChange> {
+        match packet {
+            ServerPacket::JoinGame(_) | ServerPacket::Respawn(_) => self.clear().into_iter().collect(),
+            ServerPacket::SpawnPlayer(packet) => vec![self.insert(spawn_player(packet))],
+            ServerPacket::SpawnMob(packet) => vec![self.insert(spawn_mob(packet))],
+            ServerPacket::SpawnObject(packet) => vec![self.insert(spawn_object(packet))],
+            ServerPacket::SpawnPainting(packet) => vec![self.insert(spawn_painting(packet))],
+            ServerPacket::SpawnExperienceOrb(packet) => vec![self.insert(spawn_experience_orb(packet))],
+            ServerPacket::DestroyEntities(packet) => self.apply_destroy(packet),
+            ServerPacket::EntityRelMove(packet) => {
+                self.apply_rel_move(packet);
+                Vec::new()
+            }
+            ServerPacket::EntityLook(packet) => {
+                self.apply_look(packet);
+                Vec::new()
+            }
+            ServerPacket::EntityLookMove(packet) => {
+                self.apply_look_move(packet);
+                Vec::new()
+            }
+            ServerPacket::EntityTeleport(packet) => {
+                self.apply_teleport(packet);
+                Vec::new()
+            }
+            ServerPacket::EntityHeadLook(packet) => {
+                self.apply_head_look(packet);
+                Vec::new()
+            }
+            ServerPacket::EntityVelocity(packet) => {
+                self.apply_velocity(packet);
+                Vec::new()
+            }
+            ServerPacket::EntityMetadata(packet) => {
+                self.apply_metadata(packet);
+                Vec::new()
+            }
+            ServerPacket::EntityEquipment(packet) => {
+                self.apply_equipment(packet);
+                Vec::new()
+            }
+            ServerPacket::AttachEntity(packet) => {
+                self.apply_attach(packet);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn apply_destroy(&mut self, packet: &DestroyEntities) -> Vec<EntityChange> {
+        packet.entity_ids.0.iter().filter_map(|&id| self.remove(id)).collect()
+    }
+
+    pub fn apply_rel_move(&mut self, packet: &EntityRelMove) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            move_by(entity, packet.x.0, packet.y.0, packet.z.0);
+        }
+    }
+
+    pub fn apply_look(&mut self, packet: &EntityLook) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            entity.yaw = from_angle(packet.yaw.0);
+            entity.pitch = from_angle(packet.pitch.0);
+        }
+    }
+
+    pub fn apply_look_move(&mut self, packet: &EntityLookMove) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            move_by(entity, packet.x.0, packet.y.0, packet.z.0);
+            entity.yaw = from_angle(packet.yaw.0);
+            entity.pitch = from_angle(packet.pitch.0);
+        }
+    }
+
+    pub fn apply_teleport(&mut self, packet: &EntityTeleport) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            entity.x = from_fixed(packet.x.0);
+            entity.y = from_fixed(packet.y.0);
+            entity.z = from_fixed(packet.z.0);
+            entity.yaw = from_angle(packet.yaw.0);
+            entity.pitch = from_angle(packet.pitch.0);
+        }
+    }
+
+    pub fn apply_head_look(&mut self, packet: &EntityHeadLook) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            entity.head_yaw = from_angle(packet.head_yaw.0);
+        }
+    }
+
+    pub fn apply_velocity(&mut self, packet: &EntityVelocity) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            entity.velocity = velocity(packet.velocity_x.0, packet.velocity_y.0, packet.velocity_z.0);
+        }
+    }
+
+    pub fn apply_metadata(&mut self, packet: &EntityMetadata) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            entity.update_metadata(&packet.metadata);
+        }
+    }
+
+    pub fn apply_equipment(&mut self, packet: &EntityEquipment) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            if packet.item.is_empty() {
+                entity.equipment.remove(&packet.slot.0);
+            } else {
+                entity.equipment.insert(packet.slot.0, packet.item.clone());
+            }
+        }
+    }
+
+    /// Посадка в транспорт или поводок; `vehicle_id == -1` — слез или отвязан
+    pub fn apply_attach(&mut self, packet: &AttachEntity) {
+        if let Some(entity) = self.entities.get_mut(&packet.entity_id.0) {
+            let target = (packet.vehicle_id.0 != -1).then_some(packet.vehicle_id.0);
+            if packet.leash.0 {
+                entity.leash_holder = target;
+            } else {
+                entity.vehicle = target;
+            }
+        }
+    }
+}
+
+fn move_by(entity: &mut Entity, dx: u8, dy: u8, dz: u8) {
+    entity.x += from_fixed(dx as i8 as i32);
+    entity.y += from_fixed(dy as i8 as i32);
+    entity.z += from_fixed(dz as i8 as i32);
+}
+
+fn velocity(x: i16, y: i16, z: i16) -> (f64, f64, f64) {
+    (from_velocity(x), from_velocity(y), from_velocity(z))
+}
+
+fn spawn_player(packet: &SpawnPlayer) -> Entity {
+    let (uuid, name, _) = &packet.profile.0;
+    let kind = EntityKind::Player {
+        uuid: uuid.0,
+        name: name.0.clone(),
+    };
+    let mut entity = Entity::new(
+        packet.entity_id.0,
+        kind,
+        from_fixed(packet.x.0),
+        from_fixed(packet.y.0),
+        from_fixed(packet.z.0),
+    );
+    entity.yaw = from_angle(packet.yaw.0);
+    entity.pitch = from_angle(packet.pitch.0);
+    entity.head_yaw = entity.yaw;
+    // В пакете только id предмета в руке: без количества и тега
+    if packet.current_item.0 > 0 {
+        entity.equipment.insert(0, ItemStack::new(packet.current_item.0, 1, 0));
+    }
+    entity.update_metadata(&packet.metadata);
+    entity
+}
+
+fn spawn_mob(packet: &SpawnMob) -> Entity {
+    let kind = EntityKind::Mob {
+        mob_type: packet.mob_type.0,
+    };
+    let mut entity = Entity::new(
+        packet.entity_id.0,
+        kind,
+        from_fixed(packet.x.0),
+        from_fixed(packet.y.0),
+        from_fixed(packet.z.0),
+    );
+    entity.yaw = from_angle(packet.yaw.0);
+    entity.pitch = from_angle(packet.pitch.0);
+    entity.head_yaw = from_angle(packet.head_yaw.0);
+    entity.velocity = velocity(packet.velocity_x.0, packet.velocity_y.0, packet.velocity_z.0);
+    entity.update_metadata(&packet.metadata);
+    entity
+}
+
+fn spawn_object(packet: &SpawnObject) -> Entity {
+    let kind = EntityKind::Object {
+        object_type: packet.ty.0,
+        data: packet.data.data,
+    };
+    let mut entity = Entity::new(
+        packet.entity_id.0,
+        kind,
+        from_fixed(packet.x.0),
+        from_fixed(packet.y.0),
+        from_fixed(packet.z.0),
+    );
+    entity.yaw = from_angle(packet.yaw.0);
+    entity.pitch = from_angle(packet.pitch.0);
+    if let Some((x, y, z)) = packet.data.velocity {
+        entity.velocity = velocity(x, y, z);
+    }
+    entity
+}
+
+fn spawn_painting(packet: &SpawnPainting) -> Entity {
+    let kind = EntityKind::Painting {
+        title: packet.title.0.clone(),
+        direction: packet.direction.0,
+    };
+    Entity::new(
+        packet.entity_id.0,
+        kind,
+        packet.x.0 as f64,
+        packet.y.0 as f64,
+        packet.z.0 as f64,
+    )
+}
+
+fn spawn_experience_orb(packet: &SpawnExperienceOrb) -> Entity {
+    let kind = EntityKind::ExperienceOrb {
+        count: packet.count.0,
+    };
+    Entity::new(
+        packet.entity_id.0,
+        kind,
+        from_fixed(packet.x.0),
+        from_fixed(packet.y.0),
+        from_fixed(packet.z.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::fields::uuid::Uuid as UuidField;
+    use crate::protocol::fields::{
+        Boolean, Byte, EntityIds, GameProfile, Int, ObjectData, Short, VarInt, VarString,
+    };
+
+    fn player(id: i32, name: &str, x: i32, y: i32, z: i32) -> ServerPacket {
+        ServerPacket::SpawnPlayer(SpawnPlayer {
+            entity_id: VarInt(id),
+            profile: GameProfile((UuidField(Uuid::nil()), VarString(name.to_string()), Vec::new())),
+            x: Int(x),
+            y: Int(y),
+            z: Int(z),
+            yaw: Byte(64),
+            pitch: Byte(0xE0),
+            current_item: Short(0),
+            metadata: Metadata(vec![(HEALTH_INDEX, MetadataValue::Float(20.0))]),
+        })
+    }
+
+    #[test]
+    fn spawn_and_move_convert_units() {
+        let mut tracker = EntityTracker::default();
+        let changes = tracker.apply(&player(7, "Alice", 320, 2048, -48));
+        assert_eq!(changes, [EntityChange::Spawned { id: 7 }]);
+
+        let alice = tracker.get(7).unwrap();
+        assert_eq!((alice.x, alice.y, alice.z), (10.0, 64.0, -1.5));
+        assert_eq!((alice.yaw, alice.pitch), (90.0, -45.0));
+        assert_eq!(alice.health(), Some(20.0));
+
+        tracker.apply(&ServerPacket::EntityRelMove(EntityRelMove {
+            entity_id: Int(7),
+            x: Byte(16),
+            y: Byte(-32i8 as u8),
+            z: Byte(0),
+        }));
+        tracker.apply(&ServerPacket::EntityVelocity(EntityVelocity {
+            entity_id: Int(7),
+            velocity_x: Short(8000),
+            velocity_y: Short(-4000),
+            velocity_z: Short(0),
+        }));
+        let alice = tracker.get(7).unwrap();
+        assert_eq!((alice.x, alice.y, alice.z), (10.5, 63.0, -1.5));
+        assert_eq!(alice.velocity, (1.0, -0.5, 0.0));
+    }
+
+    #[test]
+    fn queries_and_destroy() {
+        let mut tracker = EntityTracker::default();
+        tracker.apply(&player(1, "Alice", 0, 0, 0));
+        tracker.apply(&player(2, "Bob", 32 * 10, 0, 0));
+        tracker.apply(&ServerPacket::SpawnObject(SpawnObject {
+            entity_id: VarInt(3),
+            ty: Byte(10),
+            x: Int(32),
+            y: Int(0),
+            z: Int(0),
+            pitch: Byte(0),
+            yaw: Byte(0),
+            data: ObjectData::default(),
+        }));
+        tracker.apply(&ServerPacket::AttachEntity(AttachEntity {
+            entity_id: Int(1),
+            vehicle_id: Int(3),
+            leash: Boolean(false),
+        }));
+        assert_eq!(tracker.get(1).unwrap().vehicle, Some(3));
+
+        assert_eq!(tracker.nearest_player(8.0, 0.0, 0.0).unwrap().id, 2);
+        let mut near: Vec<_> = tracker.in_radius(0.0, 0.0, 0.0, 2.0).map(|e| e.id).collect();
+        near.sort();
+        assert_eq!(near, [1, 3]);
+        assert_eq!(tracker.player_by_name("Bob").unwrap().id, 2);
+
+        let changes = tracker.apply(&ServerPacket::DestroyEntities(DestroyEntities {
+            entity_ids: EntityIds(vec![3, 42]),
+        }));
+        assert_eq!(changes, [EntityChange::Removed { id: 3 }]);
+        assert_eq!(tracker.get(1).unwrap().vehicle, None);
+        assert_eq!(tracker.len(), 2);
+    }
+}
@@ -0,0 +1,427 @@
+//! Мир вокруг бота, собранный из пакетов чанков и изменений блоков.
+//! Ведётся ядром соединения, см. `ConnectionHandle::world`.
+
+pub mod chunk;
+pub mod entity;
+
+pub use chunk::{Block, ChunkColumn, ChunkSection};
+pub use entity::{Entity, EntityChange, EntityKind, EntityTracker};
+
+use crate::protocol::packets::server::{BlockChange, ChunkData, Explosion, MapChunkBulk, MultiBlockChange, ServerPacket};
+use chunk::{data_size, WORLD_HEIGHT};
+use flate2::read::ZlibDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use tokio::io;
+
+/// Измерения без неба (`hasNoSky`): там не передаётся свет неба
+const NETHER: i32 = -1;
+const THE_END: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Координаты столбца чанка
+    pub fn chunk(&self) -> (i32, i32) {
+        (self.x >> 4, self.z >> 4)
+    }
+}
+
+/// Свет в точке: от блоков и от неба (`None` в измерениях без неба)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Light {
+    pub block: u8,
+    pub sky: Option<u8>,
+}
+
+/// Что поменялось в мире. Публикуется как `EventKind::World`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldChange {
+    ChunkLoaded { x: i32, z: i32 },
+    ChunkUnloaded { x: i32, z: i32 },
+    BlockChanged { pos: BlockPos, old: Block, new: Block },
+    /// Сменилось измерение, все чанки выброшены
+    Cleared { dimension: i32 },
+}
+
+#[derive(Debug, Default)]
+pub struct World {
+    dimension: Option<i32>,
+    columns: HashMap<(i32, i32), ChunkColumn>,
+}
+
+impl World {
+    pub fn dimension(&self) -> Option<i32> {
+        self.dimension
+    }
+
+    pub fn has_sky(&self) -> bool {
+        !matches!(self.dimension, Some(NETHER | THE_END))
+    }
+
+    pub fn column(&self, chunk_x: i32, chunk_z: i32) -> Option<&ChunkColumn> {
+        self.columns.get(&(chunk_x, chunk_z))
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = &ChunkColumn> {
+        self.columns.values()
+    }
+
+    pub fn is_loaded(&self, x: i32, z: i32) -> bool {
+        self.columns.contains_key(&(x >> 4, z >> 4))
+    }
+
+    /// `None` — чанк не загружен или высота вне мира
+    pub fn block_at(&self, x: i32, y: i32, z: i32) -> Option<Block> {
+        let (column, lx, ly, lz) = self.locate(x, y, z)?;
+        Some(column.block(lx, ly, lz))
+    }
+
+    pub fn light_at(&self, x: i32, y: i32, z: i32) -> Option<Light> {
+        let (column, lx, ly, lz) = self.locate(x, y, z)?;
+        let (block, sky) = column.light(lx, ly, lz);
+        Some(Light { block, sky })
+    }
+
+    /// `None` — чанк не загружен или пришёл без биомов
+    pub fn biome_at(&self, x: i32, z: i32) -> Option<u8> {
+        self.column(x >> 4, z >> 4)?
+            .biome((x & 15) as usize, (z & 15) as usize)
+    }
+
+    /// Меняет блок локально, например чтобы предсказать результат своего действия.
+    /// `None` — чанк не загружен или блок тот же.
+    pub fn set_block(&mut self, pos: BlockPos, block: Block) -> Option<WorldChange> {
+        if !(0..WORLD_HEIGHT).contains(&pos.y) {
+            return None;
+        }
+        let column = self.columns.get_mut(&pos.chunk())?;
+        let (lx, ly, lz) = ((pos.x & 15) as usize, pos.y as usize, (pos.z & 15) as usize);
+        let old = column.block(lx, ly, lz);
+        if old == block {
+            return None;
+        }
+        column.set_block(lx, ly, lz, block);
+        Some(WorldChange::BlockChanged { pos, old, new: block })
+    }
+
+    /// Выбрасывает все чанки при смене измерения
+    pub fn set_dimension(&mut self, dimension: i32) -> Option<WorldChange> {
+        let changed = self.dimension.is_some_and(|current| current != dimension);
+        self.dimension = Some(dimension);
+        if changed {
+            self.columns.clear();
+            return Some(WorldChange::Cleared { dimension });
+        }
+        None
+    }
+
+    pub fn unload_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Option<WorldChange> {
+        self.columns
+            .remove(&(chunk_x, chunk_z))
+            .map(|_| WorldChange::ChunkUnloaded { x: chunk_x, z: chunk_z })
+    }
+
+    pub fn clear(&mut self) {
+        self.columns.clear();
+    }
+
+    /// Применяет пакет, если он касается мира
+    pub fn apply(&mut self, packet: &ServerPacket) -> io::Result<Vec<WorldChange>> {
+        let changes = match packet {
+            ServerPacket::JoinGame(packet) => {
+                // Новый вход: старые чанки к этому миру не относятся
+                self.columns.clear();
+                self.dimension = Some(packet.dimension.0 as i8 as i32);
+                Vec::new()
+            }
+            ServerPacket::Respawn(packet) => self.set_dimension(packet.dimension.0).into_iter().collect(),
+            ServerPacket::ChunkData(packet) => self.load_chunk(packet)?.into_iter().collect(),
+            ServerPacket::MapChunkBulk(packet) => self.load_chunk_bulk(packet)?,
+            ServerPacket::BlockChange(packet) => self.apply_block_change(packet).into_iter().collect(),
+            ServerPacket::MultiBlockChange(packet) => self.apply_multi_block_change(packet),
+            ServerPacket::Explosion(packet) => self.apply_explosion(packet),
+            _ => Vec::new(),
+        };
+        Ok(changes)
+    }
+
+    pub fn load_chunk(&mut self, packet: &ChunkData) -> io::Result<Option<WorldChange>> {
+        let (x, z) = (packet.x.0, packet.z.0);
+        if packet.is_unload() {
+            return Ok(self.unload_chunk(x, z));
+        }
+
+        let data = inflate(&packet.data.0)?;
+        let (primary, add, ground_up) = (packet.primary_bitmap.0, packet.add_bitmap.0, packet.ground_up.0);
+        let (without_sky, with_sky) = data_size(primary, add, ground_up);
+        let has_sky = if data.len() == with_sky && with_sky != without_sky {
+            true
+        } else if data.len() == without_sky {
+            with_sky == without_sky && self.has_sky()
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Chunk {},{} data is {} bytes, expected {} or {}", x, z, data.len(), without_sky, with_sky),
+            ));
+        };
+
+        if !ground_up {
+            // Как и клиент, обновления для незагруженного столбца пропускаем
+            if let Some(column) = self.columns.get_mut(&(x, z)) {
+                column.read_data(&data, primary, add, false)?;
+            }
+            return Ok(None);
+        }
+        let mut column = ChunkColumn::new(x, z, has_sky);
+        column.read_data(&data, primary, add, true)?;
+        self.columns.insert((x, z), column);
+        Ok(Some(WorldChange::ChunkLoaded { x, z }))
+    }
+
+    pub fn load_chunk_bulk(&mut self, packet: &MapChunkBulk) -> io::Result<Vec<WorldChange>> {
+        let bulk = &packet.bulk;
+        let data = inflate(&bulk.data)?;
+        // Сначала разбираем все столбцы: битый столбец в середине не должен оставить
+        // в мире загруженные чанки, о которых подписчики не узнают
+        let mut offset = 0;
+        let mut columns = Vec::with_capacity(bulk.columns.len());
+        for meta in &bulk.columns {
+            let mut column = ChunkColumn::new(meta.x, meta.z, bulk.sky_light);
+            offset += column.read_data(&data[offset..], meta.primary_bitmap, meta.add_bitmap, true)?;
+            columns.push(column);
+        }
+        Ok(columns
+            .into_iter()
+            .map(|column| {
+                let (x, z) = (column.x, column.z);
+                self.columns.insert((x, z), column);
+                WorldChange::ChunkLoaded { x, z }
+            })
+            .collect())
+    }
+
+    pub fn apply_block_change(&mut self, packet: &BlockChange) -> Option<WorldChange> {
+        let pos = BlockPos::new(packet.x.0, packet.y.0 as i32, packet.z.0);
+        self.set_block(pos, Block::new(packet.block_id.0 as u16, packet.meta.0))
+    }
+
+    pub fn apply_multi_block_change(&mut self, packet: &MultiBlockChange) -> Vec<WorldChange> {
+        packet
+            .records
+            .0
+            .iter()
+            .filter_map(|record| {
+                let (x, y, z) = packet.position(record);
+                self.set_block(BlockPos::new(x, y, z), Block::new(record.block_id, record.meta))
+            })
+            .collect()
+    }
+
+    /// Разрушенные взрывом блоки становятся воздухом
+    pub fn apply_explosion(&mut self, packet: &Explosion) -> Vec<WorldChange> {
+        packet
+            .affected_blocks()
+            .filter_map(|(x, y, z)| self.set_block(BlockPos::new(x, y, z), Block::AIR))
+            .collect()
+    }
+
+    fn locate(&self, x: i32, y: i32, z: i32) -> Option<(&ChunkColumn, usize, usize, usize)> {
+        if !(0..WORLD_HEIGHT).contains(&y) {
+            return None;
+        }
+        let column = self.column(x >> 4, z >> 4)?;
+        Some((column, (x & 15) as usize, y as usize, (z & 15) as usize))
+    }
+}
+
+fn inflate(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    ZlibDecoder::new(compressed).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::fields::{
+        BlockRecord, BlockRecords, Boolean, ByteArrayInt, ChunkBulk, ChunkMeta, ExplosionRecord, ExplosionRecords, Float,
+        Int, UShort,
+    };
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Столбец с одной секцией на высоте 64..80: камень (id 1) на дне, свет 15 от неба
+    fn column_data(sky: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut blocks = vec![0u8; 4096];
+        blocks[..256].fill(1);
+        data.extend_from_slice(&blocks);
+        let mut meta = vec![0u8; 2048];
+        meta[..128].fill(0x22); // meta 2 у камня
+        data.extend_from_slice(&meta);
+        data.extend_from_slice(&[0x00; 2048]); // свет от блоков
+        if sky {
+            data.extend_from_slice(&[0xFF; 2048]);
+        }
+        data.extend_from_slice(&[4u8; 256]); // лес
+        data
+    }
+
+    fn chunk_packet(x: i32, z: i32, primary: u16, data: &[u8]) -> ServerPacket {
+        ServerPacket::ChunkData(ChunkData {
+            x: Int(x),
+            z: Int(z),
+            ground_up: Boolean(true),
+            primary_bitmap: UShort(primary),
+            add_bitmap: UShort(0),
+            data: ByteArrayInt(deflate(data)),
+        })
+    }
+
+    #[test]
+    fn chunk_data_loads_and_unloads() {
+        let mut world = World::default();
+        let changes = world.apply(&chunk_packet(-1, 2, 1 << 4, &column_data(true))).unwrap();
+        assert_eq!(changes, [WorldChange::ChunkLoaded { x: -1, z: 2 }]);
+
+        // x = -16..-1, z = 32..47
+        assert_eq!(world.block_at(-16, 64, 32), Some(Block::new(1, 2)));
+        assert_eq!(world.block_at(-1, 65, 47), Some(Block::AIR));
+        assert_eq!(world.block_at(-1, 10, 47), Some(Block::AIR));
+        assert_eq!(world.block_at(0, 64, 32), None);
+        assert_eq!(world.light_at(-5, 70, 40), Some(Light { block: 0, sky: Some(15) }));
+        assert_eq!(world.biome_at(-5, 40), Some(4));
+
+        let changes = world.apply(&chunk_packet(-1, 2, 0, &[])).unwrap();
+        assert_eq!(changes, [WorldChange::ChunkUnloaded { x: -1, z: 2 }]);
+        assert!(!world.is_loaded(-16, 32));
+    }
+
+    #[test]
+    fn bulk_without_sky_and_block_changes() {
+        let mut data = column_data(false);
+        data.extend(column_data(false));
+        let bulk = MapChunkBulk {
+            bulk: ChunkBulk {
+                sky_light: false,
+                data: deflate(&data),
+                columns: vec![
+                    ChunkMeta { x: 0, z: 0, primary_bitmap: 1 << 4, add_bitmap: 0 },
+                    ChunkMeta { x: 1, z: 0, primary_bitmap: 1 << 4, add_bitmap: 0 },
+                ],
+            },
+        };
+        let mut world = World::default();
+        assert_eq!(world.apply(&ServerPacket::MapChunkBulk(bulk)).unwrap().len(), 2);
+        assert_eq!(world.block_at(20, 64, 3), Some(Block::new(1, 2)));
+        assert_eq!(world.light_at(20, 64, 3).unwrap().sky, None);
+
+        let change = world.set_block(BlockPos::new(20, 64, 3), Block::AIR);
+        assert_eq!(
+            change,
+            Some(WorldChange::BlockChanged {
+                pos: BlockPos::new(20, 64, 3),
+                old: Block::new(1, 2),
+                new: Block::AIR,
+            })
+        );
+        // Блок в пустой секции создаёт её
+        world.set_block(BlockPos::new(20, 200, 3), Block::new(0x105, 1));
+        assert_eq!(world.block_at(20, 200, 3), Some(Block::new(0x105, 1)));
+    }
+
+    #[test]
+    fn truncated_bulk_loads_nothing() {
+        // Данных хватает только на первый столбец
+        let bulk = MapChunkBulk {
+            bulk: ChunkBulk {
+                sky_light: false,
+                data: deflate(&column_data(false)),
+                columns: vec![
+                    ChunkMeta { x: 0, z: 0, primary_bitmap: 1 << 4, add_bitmap: 0 },
+                    ChunkMeta { x: 1, z: 0, primary_bitmap: 1 << 4, add_bitmap: 0 },
+                ],
+            },
+        };
+        let mut world = World::default();
+        assert!(world.apply(&ServerPacket::MapChunkBulk(bulk)).is_err());
+        assert!(!world.is_loaded(0, 0));
+    }
+
+    #[test]
+    fn respawn_into_other_dimension_clears() {
+        let mut world = World::default();
+        world.apply(&chunk_packet(0, 0, 1 << 4, &column_data(true))).unwrap();
+        assert_eq!(world.set_dimension(0), None);
+        assert!(world.is_loaded(0, 0));
+        assert_eq!(world.set_dimension(-1), Some(WorldChange::Cleared { dimension: -1 }));
+        assert!(!world.is_loaded(0, 0));
+        assert!(!world.has_sky());
+        world.set_dimension(1);
+        assert!(!world.has_sky());
+        world.set_dimension(0);
+        assert!(world.has_sky());
+    }
+
+    #[test]
+    fn multi_block_change_and_explosion() {
+        let mut world = World::default();
+        world.apply(&chunk_packet(-1, 0, 1 << 4, &column_data(true))).unwrap();
+
+        let packet = MultiBlockChange {
+            chunk_x: Int(-1),
+            chunk_z: Int(0),
+            records: BlockRecords(vec![
+                BlockRecord { x: 2, y: 65, z: 3, block_id: 0x105, meta: 1 },
+                BlockRecord { x: 0, y: 64, z: 0, block_id: 1, meta: 2 },
+            ]),
+        };
+        // Второй блок уже такой же — изменение только одно
+        let changes = world.apply(&ServerPacket::MultiBlockChange(packet)).unwrap();
+        assert_eq!(
+            changes,
+            [WorldChange::BlockChanged {
+                pos: BlockPos::new(-14, 65, 3),
+                old: Block::AIR,
+                new: Block::new(0x105, 1),
+            }]
+        );
+
+        let explosion = Explosion {
+            x: Float(-13.5),
+            y: Float(65.2),
+            z: Float(3.7),
+            radius: Float(3.0),
+            records: ExplosionRecords(vec![
+                ExplosionRecord { dx: -1, dy: 0, dz: 0 },
+                ExplosionRecord { dx: -2, dy: -1, dz: 0 },
+            ]),
+            knockback_x: Float(0.0),
+            knockback_y: Float(0.5),
+            knockback_z: Float(0.0),
+        };
+        // Центр отбрасывает дробь к нулю: (-13, 65, 3)
+        let changes = world.apply(&ServerPacket::Explosion(explosion)).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(world.block_at(-14, 65, 3), Some(Block::AIR));
+        assert_eq!(world.block_at(-15, 64, 3), Some(Block::AIR));
+        assert_eq!(world.block_at(-16, 64, 3), Some(Block::new(1, 2)));
+    }
+}